@@ -4,14 +4,28 @@ use std::io::Write;
 
 use clap::{command, arg, value_parser};
 
-use drake::drawing::{convert_line, load_points};
-use indicatif::ProgressBar;
+use drake::drawing::{Drawing, DrawingUnit};
+use drake::drawing::gcode::{is_gcode_file, GCodeProgram};
+use drake::drawing::optimise::optimise;
+use drake::drawing::simplify::simplify_mm;
+use drake::drawing::stroke_font::{StrokeFont, SIMPLEX_NAME};
+use drake::drawing::text::{render_in_box, Font, TextAlign, TextFont, TextStyle, DEFAULT_FONT};
+use drake::drawing::transform::DrawingTransform;
 use log::info;
 use syact::prelude::*;
 use sybot::prelude::*;
 
 use drake::{drake_robot_new, DrakeStation};
 use drake::config::{DrakeConfig, DrakeEnvironment, DrakeHardware};
+use drake::hal::Hal;
+use drake::job::JobSettings;
+use drake::journal::{DrawingOptions, JobJournal, Journal, JOURNAL_FILE};
+use drake::menu::{Menu, MenuAction};
+use drake::queue::JobQueue;
+use drake::routines::{calibrate_servos, check_drawing, draw_drawing, handle_halt, run_gcode, spawn_speed_console, start_drawing};
+use drake::sim::{SimConfig, VirtualDrake, SIM_PRESS_MARGIN};
+use drake::toolpath::Toolpath;
+use drake::user_terminal::{LedPattern, LONG_PRESS_TIME};
 
 #[tokio::main]
 async fn main() -> Result<(), syact::Error> {
@@ -24,10 +38,61 @@ async fn main() -> Result<(), syact::Error> {
             .about("Table testing program for the drake robot")
             .arg(arg!([command] "The command to execute").value_parser(value_parser!(String)))
             .arg(arg!([arg1] "The first argument for the command").value_parser(value_parser!(String)))
+            .arg(arg!(--optimise "Merges and reorders the strokes of a drawing to reduce pen lifts and travel"))
+            .arg(arg!(--simplify <TOLERANCE> "Simplifies the strokes of a drawing, keeping them within the given tolerance (mm)").value_parser(value_parser!(f32)))
+            .arg(arg!(--tolerance <TOLERANCE> "Maximum deviation when flattening curves of vector drawings (mm)").value_parser(value_parser!(f32)).default_value("0.1"))
+            .arg(arg!(--fit "Scales and centers the drawing to fill the paper given in the config"))
+            .arg(arg!(--size <SIZE> "Font size of texts (mm)").value_parser(value_parser!(f32)).default_value("10"))
+            .arg(arg!(--at <POS> "Position of texts relative to the drawing origin as 'X,Y' (mm)").value_parser(value_parser!(String)).default_value("0,0"))
+            .arg(arg!(--rotation <DEGREES> "Counter-clockwise rotation of texts").value_parser(value_parser!(f32)).default_value("0"))
+            .arg(arg!(--align <ALIGN> "Alignment of text lines (left, center or right)").value_parser(value_parser!(TextAlign)).default_value("left"))
+            .arg(arg!(--hatch <SPACING> "Fills the glyphs of texts with hatch lines of the given spacing (mm)").value_parser(value_parser!(f32)))
+            .arg(arg!(--font <FONT> "TrueType font file used for texts or 'simplex' for the built-in single-stroke font, defaults to the bundled Consolas").value_parser(value_parser!(String)))
+            .arg(arg!(--"line-spacing" <FACTOR> "Distance between text lines relative to the line height of the font").value_parser(value_parser!(f32)).default_value("1"))
+            .arg(arg!(--"box" <BOX> "Wraps texts into a box given as 'X,Y,WIDTH,HEIGHT' relative to the drawing origin (mm), replacing '--at'").value_parser(value_parser!(String)))
+            .arg(arg!(--job <JOB_FILE> "Loads the settings of the job (speeds and speed override) from the given file").value_parser(value_parser!(String)))
+            .arg(arg!(--"draw-speed" <SPEED> "Speed of the pen while drawing (mm/s)").value_parser(value_parser!(f32)))
+            .arg(arg!(--"travel-speed" <SPEED> "Speed of the pen while traveling between strokes (mm/s)").value_parser(value_parser!(f32)))
+            .arg(arg!(--"lift-speed" <SPEED> "Speed of the Z-axis when lifting or lowering the pen (mm/s)").value_parser(value_parser!(f32)))
+            .arg(arg!(--"speed-override" <PERCENT> "Initial speed override, adjustable while drawing by entering '+', '-' or a percentage").value_parser(value_parser!(u32)))
+            .arg(arg!(--svg <SVG_FILE> "Records all pen moves and writes them into the given SVG file").value_parser(value_parser!(String)))
+            .arg(arg!(--sim [SIM_CONFIG] "Runs the command on a simulated drake, optionally with the given simulation config").value_parser(value_parser!(String)))
             .get_matches();
 
         let command_opt : Option<String> = matches.get_one::<String>("command").map(|v| v.clone());
         let arg1_opt : Option<String> = matches.get_one::<String>("arg1").map(|v| v.clone());
+
+        let sim_mode = matches.contains_id("sim");
+        let sim_path_opt : Option<String> = matches.get_one::<String>("sim").cloned();
+        let svg_path_opt : Option<String> = matches.get_one::<String>("svg").cloned();
+        let optimise_flag = matches.get_flag("optimise");
+        let simplify_opt : Option<f32> = matches.get_one::<f32>("simplify").copied();
+        let fit_flag = matches.get_flag("fit");
+        let drawing_options = DrawingOptions {
+            tolerance: *matches.get_one::<f32>("tolerance").unwrap(),
+            optimise: optimise_flag,
+            simplify: simplify_opt
+        };
+
+        let text_style = TextStyle {
+            size: *matches.get_one::<f32>("size").unwrap(),
+            align: *matches.get_one::<TextAlign>("align").unwrap(),
+            hatch: matches.get_one::<f32>("hatch").copied(),
+            line_spacing: *matches.get_one::<f32>("line-spacing").unwrap(),
+            ..TextStyle::default()
+        };
+        let text_pos : String = matches.get_one::<String>("at").unwrap().clone();
+        let text_box_opt : Option<String> = matches.get_one::<String>("box").cloned();
+        let text_rotation = *matches.get_one::<f32>("rotation").unwrap();
+        let font_path_opt : Option<String> = matches.get_one::<String>("font").cloned();
+
+        let job_path_opt : Option<String> = matches.get_one::<String>("job").cloned();
+        let cli_settings = JobSettings {
+            draw_speed: matches.get_one::<f32>("draw-speed").copied(),
+            travel_speed: matches.get_one::<f32>("travel-speed").copied(),
+            lift_speed: matches.get_one::<f32>("lift-speed").copied(),
+            speed_override: matches.get_one::<u32>("speed-override").copied()
+        };
     //  
 
     // Header
@@ -39,9 +104,6 @@ async fn main() -> Result<(), syact::Error> {
     // 
 
     // Config
-        let hardware = DrakeHardware::parse_from_env().unwrap();
-        info!("| > Loading hardware from variables done!");
-
         let environment = DrakeEnvironment::parse_from_env().unwrap();
         info!("| > Loading environment from variables done!");
 
@@ -49,17 +111,158 @@ async fn main() -> Result<(), syact::Error> {
         info!("| > Loading config at path '{}' ... ", &environment.config_path); 
     // 
 
+    // Job
+        let job_settings = match &job_path_opt {
+            Some(path) => JobSettings::parse_from_file(path).unwrap().merge(&cli_settings),
+            None => cli_settings
+        };
+    // 
+
+    // Drawings
+        // Optimises and simplifies a loaded drawing, simplifying with the scale of the transform it is drawn with
+        let process_drawing = |mut drawing : Drawing, options : &DrawingOptions, transform : &DrawingTransform| -> Drawing {
+            if options.optimise {
+                let (optimised, report) = optimise(&drawing);
+                info!("| > Optimised drawing: {}", report);
+                drawing = optimised;
+            }
+
+            if let Some(tolerance) = options.simplify {
+                let simplified = simplify_mm(&drawing, tolerance, transform.units_per_mm);
+                info!("| > Simplified drawing with tolerance {}mm: lines: {} -> {}", tolerance, drawing.line_count(), simplified.line_count());
+                drawing = simplified;
+            }
+
+            drawing
+        };
+
+        let load_drawing = |path : &str, options : &DrawingOptions, fit : bool| -> Result<(Drawing, DrawingTransform), syact::Error> {
+            let drawing = Drawing::load(path, options.tolerance)?;
+            let mut transform = DrawingTransform::from_config(&config, drawing.unit);
+
+            if fit {
+                let paper_size = config.paper_size.ok_or("Fitting a drawing requires the paper size in the config!")?;
+                transform.fit_to_paper(&drawing, paper_size, 0.0);
+                info!("| > Fitted drawing onto paper: {:?}", transform);
+            }
+
+            Ok((process_drawing(drawing, options, &transform), transform))
+        };
+
+        let load_gcode = |path : &str, fit : bool| -> Result<(GCodeProgram, DrawingTransform), syact::Error> {
+            let program = GCodeProgram::load(path)?;
+            let mut transform = DrawingTransform::from_config(&config, DrawingUnit::Millimeter);
+
+            if fit {
+                let paper_size = config.paper_size.ok_or("Fitting a drawing requires the paper size in the config!")?;
+                transform.fit_to_paper(&program.to_drawing(), paper_size, 0.0);
+                info!("| > Fitted program onto paper: {:?}", transform);
+            }
+
+            Ok((program, transform))
+        };
+
+        // Parses a list of numbers given as 'A,B,...', which has to have the given length
+        let parse_numbers = |value : &str, count : usize, name : &str| -> Result<Vec<f32>, syact::Error> {
+            let numbers = value.split(',').map(|v| v.trim().parse::<f32>()).collect::<Result<Vec<f32>, _>>()
+                .map_err(|_| format!("Invalid {} '{}'!", name, value))?;
+
+            if numbers.len() != count {
+                return Err(format!("Invalid {} '{}', {} values are required!", name, value, count).into());
+            }
+
+            Ok(numbers)
+        };
+
+        let render_text = |text : &str| -> Result<(Drawing, DrawingTransform), syact::Error> {
+            let font : Box<dyn TextFont> = match font_path_opt.as_deref() {
+                Some(SIMPLEX_NAME) => Box::new(StrokeFont::simplex()),
+                Some(path) => Box::new(Font::load(path)?),
+                None => Box::new(Font::load(&format!("{}/{}", environment.ctrl_dir, DEFAULT_FONT))?)
+            };
+
+            // Allow line breaks to be given as '\\n' on the command line
+            let text = text.replace("\\n", "\n");
+
+            let mut transform = DrawingTransform::scale(1.0);
+            transform.rotation = text_rotation.to_radians();
+
+            let drawing = if let Some(text_box) = &text_box_opt {
+                let text_box = parse_numbers(text_box, 4, "text box")?;
+                transform.offset = [ text_box[0], text_box[1] ];
+                render_in_box(font.as_ref(), &text, &text_style, [ text_box[2], text_box[3] ])
+            } else {
+                let text_pos = parse_numbers(&text_pos, 2, "text position")?;
+                transform.offset = [ text_pos[0], text_pos[1] ];
+                font.render(&text, &text_style)
+            };
+
+            Ok((drawing, transform))
+        };
+    //  
+
+    // Offline commands
+        if command_opt.as_deref() == Some("plot_file") {
+            let path = arg1_opt.unwrap();
+            let svg_path = svg_path_opt.unwrap_or(format!("{}.plot.svg", path.trim_end_matches(".json")));
+
+            let (drawing, transform) = load_drawing(&path, &drawing_options, fit_flag)?;
+            Toolpath::from_drawing(&drawing, &transform).save_svg(&svg_path)?;
+            info!("> Plotted lines of '{}' into '{}'!", path, svg_path);
+
+            return Ok(());
+        }
+
+        if command_opt.as_deref() == Some("plot_text") {
+            let text = arg1_opt.unwrap();
+            let svg_path = svg_path_opt.unwrap_or(String::from("text.plot.svg"));
+
+            let (drawing, transform) = render_text(&text)?;
+            Toolpath::from_drawing(&drawing, &transform).save_svg(&svg_path)?;
+            info!("> Plotted text '{}' into '{}'!", text, svg_path);
+
+            return Ok(());
+        }
+    // 
+
     // Hardware
-        let gpio = rppal::gpio::Gpio::new().unwrap();
-        info!("| > Loading GPIO done!");
+        let hardware = if sim_mode {
+            DrakeHardware::parse_from_env().unwrap_or_else(|_| VirtualDrake::hardware())
+        } else {
+            DrakeHardware::parse_from_env().unwrap()
+        };
+        info!("| > Loading hardware from variables done!");
+
+
+        let virtual_drake = if sim_mode {
+            let sim_config = match &sim_path_opt {
+                Some(path) => SimConfig::parse_from_file(path).unwrap(),
+                None => SimConfig::from_config(&config)
+            };
+
+            Some(VirtualDrake::new(&hardware, &config, &sim_config))
+        } else {
+            None
+        };
+
+        let hal = if let Some(drake) = &virtual_drake {
+            drake.hold_start();
+            info!("| > Running on a simulated drake!");
+            drake.hal()
+        } else {
+            Hal::platform().unwrap()
+        };
+
+        if hal.is_virtual() && !sim_mode {
+            log::warn!("| > Built without the 'rasp' feature, running on virtual hardware!");
+        }
 
-        let i2c = rppal::i2c::I2c::new().unwrap();
-        info!("| > Loading I2C done!");
+        info!("| > Loading GPIO done!");
     // 
 
     // RDS
-        let mut rob = drake_robot_new(&hardware, &config, &gpio).unwrap();
-        let mut stat = DrakeStation::new(&hardware, &config, &gpio, i2c).unwrap();
+        let mut stat = DrakeStation::new(&hardware, &config, &hal).unwrap();
+        let mut rob = drake_robot_new(&hardware, &config, &hal, &stat).unwrap();
     // 
 
     // Init
@@ -70,61 +273,231 @@ async fn main() -> Result<(), syact::Error> {
     stat.setup().unwrap();
     stat.servo_table.set_all_open().unwrap();
 
+    if svg_path_opt.is_some() {
+        stat.toolpath = Some(Toolpath::new());
+    }
+
+    stat.speeds.apply(&job_settings);
+    stat.speed_override.set_percent(job_settings.speed_override.unwrap_or(100));
+
+    info!("| > Speeds: {:?}, override: {}%", stat.speeds, stat.speed_override.percent());
+
     let cmd = command_opt.unwrap_or(String::from("help"));
 
     info!("> Executing command: '{}'", cmd);
 
+    let journal_path = format!("{}/{}", environment.log_path, JOURNAL_FILE);
+
     if cmd == "draw_file" {
-        stat.user_terminal.prompt_start();
+        let path = arg1_opt.unwrap();
+        let (drawing, transform) = load_drawing(&path, &drawing_options, fit_flag)?;
 
-        stat.home(&mut rob).await?;
+        log::info!("> Loaded points from file '{}'!", path);
 
-        stat.servo_table.set_all_closed().unwrap();
+        check_drawing(&stat, &drawing, &transform)?;
 
-        rob.move_abs_j(config.drawing_origin, Factor::HALF).await.unwrap();
+        let mut journal = Journal::create(&journal_path, JobJournal::new(
+            &path, drawing_options.clone(), transform.clone(), job_settings.clone(), drawing.line_count()
+        )?)?;
 
-        log::info!("> Moving to drawing position done!");
+        log::info!("> Recording progress in journal '{}'", journal_path);
 
-        std::thread::sleep(std::time::Duration::from_millis(1000));
+        stat.user_terminal.prompt_start().await;
 
-        let path = arg1_opt.unwrap();
-        let lines = load_points(&path);
+        let _halt_monitor = stat.spawn_halt_monitor();
+        let _pause_monitor = stat.spawn_pause_monitor();
 
-        log::info!("> Loaded points from file '{}'!", path);
+        handle_halt(async {
+            start_drawing(&mut stat, &mut rob).await?;
 
-        let pb = ProgressBar::new(lines.contour.len() as u64);
+            std::thread::sleep(std::time::Duration::from_millis(1000));
 
-        // Safe to use
-        let mut last_point = unsafe { core::mem::zeroed() };
+            spawn_speed_console(stat.speed_override.clone(), stat.pause.clone());
+
+            draw_drawing(&mut stat, &mut rob, &drawing, &transform, Some(&mut journal)).await
+        }.await)?;
         
+    } else if cmd == "resume" {
+        let mut journal = Journal::load(&journal_path)?;
+        journal.job.verify()?;
 
-        if let Some(&init_line) = lines.contour.first() {
-            let [ p1, _ ] = convert_line(init_line);
-            stat.reposition_pen(&mut rob, p1).await.unwrap();   
-            last_point = p1;
-        }
+        // Repeat the processing with the transform of the job, which might have been fitted onto the paper
+        let transform = journal.job.transform.clone();
+        let drawing = process_drawing(Drawing::load(&journal.job.source, journal.job.options.tolerance)?, &journal.job.options, &transform);
+        journal.job.verify_lines(drawing.line_count())?;
+
+        let done = journal.job.stats.lines_done;
+
+        log::info!("> Resuming job '{}' at line {} of {}", journal.job.source, done, journal.job.stats.lines_total);
+
+        // The settings of the job take precedence over the ones given now
+        let settings = job_settings.merge(&journal.job.settings);
+        stat.speeds.apply(&settings);
+        stat.speed_override.set_percent(settings.speed_override.unwrap_or(100));
+
+        journal.job.stats.resumes += 1;
+        journal.save()?;
+
+        let remaining = drawing.skip_lines(done);
+        check_drawing(&stat, &remaining, &transform)?;
+
+        stat.user_terminal.prompt_start().await;
+
+        let _halt_monitor = stat.spawn_halt_monitor();
+        let _pause_monitor = stat.spawn_pause_monitor();
+
+        handle_halt(async {
+            start_drawing(&mut stat, &mut rob).await?;
+
+            spawn_speed_console(stat.speed_override.clone(), stat.pause.clone());
+
+            draw_drawing(&mut stat, &mut rob, &remaining, &transform, Some(&mut journal)).await
+        }.await)?;
 
-        for line in lines.contour {
-            let [ p1, p2 ] = convert_line(line);
+    } else if cmd == "menu" {
+        // # menu
+        // Runs the station standalone, all actions are selected with the buttons of the user terminal
+        let queue = JobQueue::new(&environment.queue_path);
+        let mut menu = Menu::new(&stat.user_terminal);
 
-            if p1 != last_point {
-                stat.reposition_pen(&mut rob, p1).await.unwrap();
+        // The start button held down in simulations would never let a gesture start, select the next job once instead
+        let _sim_presses = virtual_drake.as_ref().map(|drake| drake.play_start_presses(vec![
+            (LONG_PRESS_TIME + SIM_PRESS_MARGIN, SIM_PRESS_MARGIN),     // Long press: next job
+            (SIM_PRESS_MARGIN, SIM_PRESS_MARGIN)                        // Short press: confirm
+        ]));
+
+        info!("> Button menu started with {} queued jobs in '{}'", queue.jobs()?.len(), environment.queue_path);
+        info!("| > Start double: home, start long: next job, halt double: table test, start + halt long: shutdown");
+
+        loop {
+            let action = menu.select(&mut stat.user_terminal).await;
+
+            info!("> Menu: {}", action);
+            stat.user_terminal.set_halt_led(false);
+
+            let result = match action {
+                MenuAction::Home => stat.home(&mut rob).await,
+                MenuAction::NextJob => {
+                    let _halt_monitor = stat.spawn_halt_monitor();
+                    let _pause_monitor = stat.spawn_pause_monitor();
+
+                    handle_halt(async {
+                        let job = queue.next()?;
+                        let path = job.to_string_lossy().to_string();
+
+                        // Jobs that cannot be loaded or exceed the limits would fail again every time
+                        let reject = |err : syact::Error| -> Result<(), syact::Error> {
+                            let failed = queue.fail(&job)?;
+                            log::error!("| > Job '{}' cannot be drawn, moved to '{}'", path, failed.display());
+                            Err(err)
+                        };
+
+                        if is_gcode_file(&path) {
+                            // Executed as program, keeping its feed rates, dwell times and homing
+                            let loaded = load_gcode(&path, fit_flag)
+                                .and_then(|(program, transform)| check_drawing(&stat, &program.to_drawing(), &transform).map(|_| (program, transform)));
+
+                            let (program, transform) = match loaded {
+                                Ok(loaded) => loaded,
+                                Err(err) => return reject(err)
+                            };
+
+                            info!("| > Running queued G-code program '{}'", path);
+
+                            start_drawing(&mut stat, &mut rob).await?;
+                            run_gcode(&mut stat, &mut rob, &program, &transform).await?;
+                        } else {
+                            let loaded = load_drawing(&path, &drawing_options, fit_flag)
+                                .and_then(|(drawing, transform)| check_drawing(&stat, &drawing, &transform).map(|_| (drawing, transform)));
+
+                            let (drawing, transform) = match loaded {
+                                Ok(loaded) => loaded,
+                                Err(err) => return reject(err)
+                            };
+
+                            info!("| > Drawing queued job '{}'", path);
+
+                            let mut journal = Journal::create(&journal_path, JobJournal::new(
+                                &path, drawing_options.clone(), transform.clone(), job_settings.clone(), drawing.line_count()
+                            )?)?;
+
+                            start_drawing(&mut stat, &mut rob).await?;
+                            draw_drawing(&mut stat, &mut rob, &drawing, &transform, Some(&mut journal)).await?;
+                        }
+
+                        let done = queue.complete(&job)?;
+                        info!("| > Job done, moved to '{}'", done.display());
+
+                        Ok(())
+                    }.await)
+                },
+                MenuAction::TestTable => stat.servo_table.roll_servos(1.0).await.map_err(|err| err.into()),
+                MenuAction::Shutdown => break
+            };
+
+            if let Err(err) = result {
+                log::error!("> Menu action {} failed! {}", action, err);
+                stat.user_terminal.set_halt_pattern(LedPattern::ErrorCode(action.code()));
             }
+        }
 
-            rob.move_abs_j(
-                [ p2[0] + Delta(stat.drawing_origin[0].0), p2[1] + Delta(stat.drawing_origin[1].0), stat.drawing_origin[2]], 
-                Factor::new(0.5)
-            ).await.unwrap();
-            
-            last_point = p2;
+        info!("> Shutting down ... ");
 
-            pb.inc(1);
+        stat.lift_pen(&mut rob).await?;
+        stat.servo_table.set_all_open()?;
+
+        if !hal.is_virtual() {
+            if let Err(err) = std::process::Command::new("shutdown").args([ "-h", "now" ]).status() {
+                log::error!("> Failed to shut down the controller! {}", err);
+            }
         }
 
-        pb.finish_with_message("done");
-        
+    } else if cmd == "draw_text" {
+        let text = arg1_opt.unwrap();
+        let (drawing, transform) = render_text(&text)?;
+
+        log::info!("> Rendered text '{}' into {} strokes!", text, drawing.strokes.len());
+
+        check_drawing(&stat, &drawing, &transform)?;
+
+        stat.user_terminal.prompt_start().await;
+
+        let _halt_monitor = stat.spawn_halt_monitor();
+        let _pause_monitor = stat.spawn_pause_monitor();
+
+        handle_halt(async {
+            start_drawing(&mut stat, &mut rob).await?;
+
+            spawn_speed_console(stat.speed_override.clone(), stat.pause.clone());
+
+            draw_drawing(&mut stat, &mut rob, &drawing, &transform, None).await
+        }.await)?;
+
+    } else if cmd == "draw_gcode" {
+        let path = arg1_opt.unwrap();
+        let (program, transform) = load_gcode(&path, fit_flag)?;
+
+        log::info!("> Loaded G-code program '{}' with {} operations!", path, program.ops.len());
+
+        check_drawing(&stat, &program.to_drawing(), &transform)?;
+
+        stat.user_terminal.prompt_start().await;
+
+        let _halt_monitor = stat.spawn_halt_monitor();
+        let _pause_monitor = stat.spawn_pause_monitor();
+
+        handle_halt(async {
+            start_drawing(&mut stat, &mut rob).await?;
+
+            spawn_speed_console(stat.speed_override.clone(), stat.pause.clone());
+
+            run_gcode(&mut stat, &mut rob, &program, &transform).await
+        }.await)?;
+
+        log::info!("> Program done!");
+
     } else if cmd == "calibrate_x" {
-        stat.user_terminal.prompt_start();
+        stat.user_terminal.prompt_start().await;
 
         info!("> Driving to home position ... ");
         stat.home(&mut rob).await?;
@@ -144,7 +517,7 @@ async fn main() -> Result<(), syact::Error> {
         }
 
     } else if cmd == "calibrate_y" {
-        stat.user_terminal.prompt_start();
+        stat.user_terminal.prompt_start().await;
 
         info!("> Driving to home position ... ");
         stat.home(&mut rob).await?;
@@ -164,7 +537,7 @@ async fn main() -> Result<(), syact::Error> {
         }
 
     } else if cmd == "calibrate_z" {
-        stat.user_terminal.prompt_start();
+        stat.user_terminal.prompt_start().await;
 
         info!("> Driving to home position ... ");
         stat.home(&mut rob).await?;
@@ -186,12 +559,49 @@ async fn main() -> Result<(), syact::Error> {
         }
 
     } else if cmd == "prompt_start" {
-        stat.user_terminal.prompt_start();
+        stat.user_terminal.prompt_start().await;
 
 
     } else if cmd == "prompt_halt" {
-        stat.user_terminal.prompt_halt();
+        stat.user_terminal.prompt_halt().await;
+
 
+    } else if cmd == "calibrate_servos" {
+        // # calibrate_servos [ID]
+        // Calibrates all servos of the table or only the one with the given id, saving the result in the config
+        let ids : Vec<u8> = match &arg1_opt {
+            Some(id) => vec![ id.parse().unwrap() ],
+            None => (0 .. 8).collect()
+        };
+
+        stat.servo_table.set_all_open().unwrap();
+
+        if calibrate_servos(&mut stat.servo_table, &ids)? {
+            let mut new_config = config.clone();
+            new_config.servos = stat.servo_table.calibration;
+            new_config.save_to_file(&environment.config_path)?;
+
+            info!("> Saved servo calibration into '{}'", environment.config_path);
+        } else {
+            info!("> Servo calibration discarded!");
+        }
+
+    } else if cmd == "servo_pose" {
+        // # servo_pose <NAME>
+        // Moves the servos of the table to a named pose of the config
+        let name = arg1_opt.unwrap();
+
+        stat.servo_table.move_to_pose(&name).await?;
+        info!("> Servos are now in pose '{}'!", name);
+
+    } else if cmd == "servo_sequence" {
+        // # servo_sequence <NAME>
+        // Plays a named sequence of poses of the config
+        let name = arg1_opt.unwrap();
+
+        info!("> Playing servo sequence '{}' ... ", name);
+        stat.servo_table.run_sequence(&name).await?;
+        info!("| > Sequence done!");
 
     } else if cmd == "test_table" {
         // # test_table 
@@ -219,12 +629,12 @@ async fn main() -> Result<(), syact::Error> {
                 info!("| > Servo with id {} now open", id);
                 stat.servo_table.set_servo_open(id).unwrap();
 
-                stat.user_terminal.prompt_start();
+                stat.user_terminal.prompt_start().await;
 
                 info!("| > Servo with id {} now closed", id);
                 stat.servo_table.set_servo_open(id).unwrap();
 
-                stat.user_terminal.prompt_start();
+                stat.user_terminal.prompt_start().await;
 
                 stat.servo_table.set_servo_standby(id).unwrap();
             }
@@ -233,7 +643,7 @@ async fn main() -> Result<(), syact::Error> {
         } else if state == "roll" {
             info!("> Rolling servos ... ");
 
-            stat.servo_table.roll_servos(1.0).unwrap();
+            stat.servo_table.roll_servos(1.0).await.unwrap();
 
             info!("| > Rolling done!");
 
@@ -244,5 +654,14 @@ async fn main() -> Result<(), syact::Error> {
         info!("> Unknown command");
     }
 
+    if let Some(drake) = &virtual_drake {
+        info!("> Simulated positions: {:?}", drake.gammas());
+    }
+
+    if let (Some(toolpath), Some(path)) = (&stat.toolpath, &svg_path_opt) {
+        toolpath.save_svg(path)?;
+        info!("> Toolpath written to '{}'!", path);
+    }
+
     Ok(())
 }
\ No newline at end of file
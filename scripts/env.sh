@@ -7,6 +7,7 @@ echo "Setting environment variables ..."
 export DRAI_CTRL_PATH="~/drai_ctrl"
 export DRAI_LOG_PATH="logs"
 export DRAI_CONFIG_PATH="config/drake.json"
+export DRAI_QUEUE_PATH="queue"
 
 ## Networking
 export DRAI_CAMERA_PORT=40324
@@ -24,6 +25,7 @@ export RUST_LOG=info
 
 # Hardware
 ## Stepper 
+### Second end switches are optional, leave their pins empty if they are not connected
 export DRAI_CTRL_VOLTAGE=24
 
 ### X-Axis
@@ -31,7 +33,7 @@ export DRAI_X_AXIS_STEP_PIN=24
 export DRAI_X_AXIS_DIR_PIN=15
 
 export DRAI_X_SWITCH_POS_PIN=23
-export DRAI_X_SWITCH_NEG_PIN=0
+export DRAI_X_SWITCH_NEG_PIN=
 
 export DRAI_X_MICROSTEPS=8
 
@@ -40,7 +42,7 @@ export DRAI_Y_AXIS_STEP_PIN=5
 export DRAI_Y_AXIS_DIR_PIN=25
 
 export DRAI_Y_SWITCH_POS_PIN=12
-export DRAI_Y_SWITCH_NEG_PIN=0
+export DRAI_Y_SWITCH_NEG_PIN=
 
 export DRAI_Y_MICROSTEPS=8
 
@@ -48,7 +50,7 @@ export DRAI_Y_MICROSTEPS=8
 export DRAI_Z_AXIS_STEP_PIN=16
 export DRAI_Z_AXIS_DIR_PIN=6
 
-export DRAI_Z_SWITCH_POS_PIN=0
+export DRAI_Z_SWITCH_POS_PIN=
 export DRAI_Z_SWITCH_NEG_PIN=19
 
 export DRAI_Z_MICROSTEPS=1
@@ -1,10 +1,14 @@
 use core::str::FromStr;
+use std::collections::HashMap;
 
 use serde::{Serialize, Deserialize};
 use syact::meas::SimpleMeasParams;
 use syact::MicroSteps;
 use syunit::*;
 
+use crate::pen_lift::PenLiftConfig;
+use crate::servo_table::{ServoCalibration, ServoPose, ServoSequence, ServoTransition, DEFAULT_CALIBRATION};
+
 pub fn parse_env<F : FromStr>(key : &str) -> Result<F, syact::Error> {
     Ok(std::env::var(key).map_err(|v| {
         format!("Failed to load from env! Var '{}' not found! Original error: {}", key, v)
@@ -13,6 +17,14 @@ pub fn parse_env<F : FromStr>(key : &str) -> Result<F, syact::Error> {
     })?)
 }
 
+/// Like `parse_env()`, but `None` if the variable is not set or empty
+pub fn parse_env_opt<F : FromStr>(key : &str) -> Result<Option<F>, syact::Error> {
+    match std::env::var(key) {
+        Ok(value) if !value.trim().is_empty() => Ok(Some(parse_env(key)?)),
+        _ => Ok(None)
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DrakeHardware {
     pub voltage : f32,
@@ -25,13 +37,19 @@ pub struct DrakeHardware {
     pub y_dir : u8,
     pub z_dir : u8,
 
+    /// End switches used for measurements
     pub x_meas_pos : u8,
-    pub x_meas_neg : u8,
-
     pub y_meas_pos : u8,
-
     pub z_meas_neg : u8,
 
+    /// Optional end switches at the other end of each axis
+    #[serde(default)]
+    pub x_meas_neg : Option<u8>,
+    #[serde(default)]
+    pub y_meas_neg : Option<u8>,
+    #[serde(default)]
+    pub z_meas_pos : Option<u8>,
+
     pub x_microsteps : MicroSteps,
     pub y_microsteps : MicroSteps,
     pub z_microsteps : MicroSteps,
@@ -56,12 +74,13 @@ impl DrakeHardware {
             z_dir: parse_env("DRAI_Z_AXIS_DIR_PIN")?,
 
             x_meas_pos: parse_env("DRAI_X_SWITCH_POS_PIN")?,
-            x_meas_neg: parse_env("DRAI_X_SWITCH_NEG_PIN")?,
-
             y_meas_pos: parse_env("DRAI_Y_SWITCH_POS_PIN")?,
-
             z_meas_neg: parse_env("DRAI_Z_SWITCH_NEG_PIN")?,
 
+            x_meas_neg: parse_env_opt("DRAI_X_SWITCH_NEG_PIN")?,
+            y_meas_neg: parse_env_opt("DRAI_Y_SWITCH_NEG_PIN")?,
+            z_meas_pos: parse_env_opt("DRAI_Z_SWITCH_POS_PIN")?,
+
             x_microsteps: parse_env("DRAI_X_MICROSTEPS")?,
             y_microsteps: parse_env("DRAI_Y_MICROSTEPS")?,
             z_microsteps: parse_env("DRAI_Z_MICROSTEPS")?,
@@ -78,7 +97,9 @@ impl DrakeHardware {
 pub struct DrakeEnvironment {
     pub ctrl_dir : String,
     pub log_path : String,
-    pub config_path : String
+    pub config_path : String,
+    /// Directory of the jobs drawn from the button menu
+    pub queue_path : String
 }
 
 impl DrakeEnvironment {
@@ -86,29 +107,117 @@ impl DrakeEnvironment {
         Ok(Self {
             ctrl_dir: parse_env("DRAI_CTRL_PATH")?,
             log_path: parse_env("DRAI_LOG_PATH")?,
-            config_path: parse_env("DRAI_CONFIG_PATH")?
+            config_path: parse_env("DRAI_CONFIG_PATH")?,
+            queue_path: parse_env_opt("DRAI_QUEUE_PATH")?.unwrap_or(String::from("queue"))
         })
     }
 }
 
+fn default_max_speed() -> f32 {
+    50.0
+}
+
+fn default_travel_speed() -> f32 {
+    40.0
+}
+
+fn default_lift_speed() -> f32 {
+    10.0
+}
+
+fn default_junction_deviation() -> f32 {
+    0.05
+}
+
+fn default_lookahead() -> usize {
+    16
+}
+
+fn default_servos() -> [ServoCalibration; 8] {
+    DEFAULT_CALIBRATION
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DrakeConfig {
     pub home : [Phi; 3],
     pub drawing_origin : [Phi; 3],
+    /// Height the pen is lifted by between strokes, if no pen lift is configured (mm)
     pub z_lift : Delta,
+    /// Device moving the pen up and down, defaults to the Z-axis moving between the drawing origin and `z_lift` above it
+    #[serde(default)]
+    pub pen_lift : Option<PenLiftConfig>,
 
     pub ratio_x : f32,
     pub ratio_y : f32,
     pub ratio_z : f32,
 
+    /// Speed of the X-, Y- and Z-axis at full speed factor (mm/s)
+    #[serde(default = "default_max_speed")]
+    pub max_speed_x : f32,
+    #[serde(default = "default_max_speed")]
+    pub max_speed_y : f32,
+    #[serde(default = "default_max_speed")]
+    pub max_speed_z : f32,
+
+    /// Soft limits (min, max) of the X-, Y- and Z-axis in absolute positions (mm), no moves outside of them are allowed
+    #[serde(default)]
+    pub limits_x : Option<[f32; 2]>,
+    #[serde(default)]
+    pub limits_y : Option<[f32; 2]>,
+    #[serde(default)]
+    pub limits_z : Option<[f32; 2]>,
+
     pub weights : [Inertia; 3],
 
+    /// Maximum acceleration of the X- and Y-axis (mm/s²), derived from the motors and weights if not given
+    #[serde(default)]
+    pub max_accel : Option<[f32; 2]>,
+    /// Maximum deviation of the pen from a corner when passing it without stopping (mm)
+    #[serde(default = "default_junction_deviation")]
+    pub junction_deviation : f32,
+    /// Amount of segments the motion planner looks ahead
+    #[serde(default = "default_lookahead")]
+    pub lookahead : usize,
+
     pub meas_data_x : SimpleMeasParams,
     pub meas_data_y : SimpleMeasParams,
     pub meas_data_z : SimpleMeasParams,
 
     pub pixel_per_mm : f32,
-    pub drawing_speed_default : f32
+    /// Speed of the pen while drawing (mm/s), replaces the speed factor `drawing_speed_default` of older configs
+    pub drawing_speed : f32,
+    /// Speed of the pen while traveling between strokes (mm/s)
+    #[serde(default = "default_travel_speed")]
+    pub travel_speed : f32,
+    /// Speed of the Z-axis when lifting or lowering the pen (mm/s)
+    #[serde(default = "default_lift_speed")]
+    pub lift_speed : f32,
+
+    /// Rotation of pixel drawings on the paper (rad)
+    #[serde(default)]
+    pub drawing_rotation : f32,
+    /// Mirroring of pixel drawings (flip X, flip Y)
+    #[serde(default)]
+    pub drawing_mirror : [bool; 2],
+    /// Offset of pixel drawings relative to the drawing origin (mm)
+    #[serde(default)]
+    pub drawing_offset : [f32; 2],
+    /// Size of the paper starting at the drawing origin (mm), required to fit drawings onto it
+    #[serde(default)]
+    pub paper_size : Option<[f32; 2]>,
+
+    /// Calibration of the servos of the table, created with the `calibrate_servos` command
+    #[serde(default = "default_servos")]
+    pub servos : [ServoCalibration; 8],
+    /// Named angles of all table servos (rad), in addition to "open", "closed" and "standby"
+    #[serde(default)]
+    pub servo_poses : HashMap<String, ServoPose>,
+    /// Named sequences of poses, started with the `servo_sequence` command
+    #[serde(default)]
+    pub servo_sequences : HashMap<String, ServoSequence>,
+    /// Transition of the servos when moving to a pose
+    #[serde(default)]
+    pub servo_transition : ServoTransition
 }
 
 impl DrakeConfig {
@@ -117,4 +226,9 @@ impl DrakeConfig {
             std::fs::read_to_string(path)?.as_str()
         )?)
     }
+
+    pub fn save_to_file(&self, path : &str) -> Result<(), syact::Error> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,352 @@
+use core::time::Duration;
+
+use crate::drawing::{Drawing, DrawingUnit, Polyline};
+
+/// The pen counts as lowered for all Z-positions at or below this height (mm)
+pub const PEN_DOWN_Z_MAX : f32 = 0.0;
+/// Extensions of G-code files (lowercase)
+pub const GCODE_EXTENSIONS : [&str; 3] = [ "gcode", "gc", "nc" ];
+
+/// Returns true if the file is a G-code program, judging by its extension
+pub fn is_gcode_file(path : &str) -> bool {
+    std::path::Path::new(path).extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| GCODE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+// Errors
+    #[derive(Debug, Clone)]
+    pub enum GCodeError {
+        /// A word of the given line could not be parsed
+        BadWord(usize, String),
+        /// The given line uses a code that cannot be executed by the drake
+        Unsupported(usize, String)
+    }
+
+    impl core::fmt::Display for GCodeError {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            match self {
+                Self::BadWord(line, word) => f.write_fmt(format_args!("BadWord: The word '{word}' in line {line} is invalid!")),
+                Self::Unsupported(line, code) => f.write_fmt(format_args!("Unsupported: The code '{code}' in line {line} is not supported!"))
+            }
+        }
+    }
+
+    impl std::error::Error for GCodeError { }
+//
+
+/// A single operation of a G-code program, with all positions absolute in millimeters
+#[derive(Clone, Debug, PartialEq)]
+pub enum GCodeOp {
+    /// Move with the pen lifted
+    Travel([f32; 2]),
+    /// Move with the pen lowered, optionally with a feed rate (mm/s)
+    Draw { to : [f32; 2], feed : Option<f32> },
+    /// Home the robot (G28)
+    Home,
+    /// Wait for the given duration (G4)
+    Dwell(Duration)
+}
+
+/// A parsed G-code program
+#[derive(Clone, Debug, Default)]
+pub struct GCodeProgram {
+    pub ops : Vec<GCodeOp>
+}
+
+/// Modal state of the parser
+struct ParserState {
+    pos : [f32; 3],
+    relative : bool,
+    /// Millimeters per program unit (G20 / G21)
+    unit : f32,
+    rapid : bool,
+    pen_down : bool,
+    feed : Option<f32>
+}
+
+/// Removes comments (`; ...` and `( ... )`), line numbers and checksums
+fn strip_line(line : &str) -> String {
+    let line = line.split(';').next().unwrap_or("");
+    let line = line.split('*').next().unwrap_or("");
+
+    let mut result = String::with_capacity(line.len());
+    let mut depth = 0;
+
+    for c in line.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = (depth - 1).max(0),
+            _ if depth == 0 => result.push(c.to_ascii_uppercase()),
+            _ => { }
+        }
+    }
+
+    result
+}
+
+/// Splits a stripped line into its words, e.g. `G1X10 Y-2.5` into `[('G', 1.0), ('X', 10.0), ('Y', -2.5)]`
+fn split_words(line : &str, line_nr : usize) -> Result<Vec<(char, f32)>, GCodeError> {
+    let mut words = Vec::new();
+    let mut chars = line.chars().filter(|c| !c.is_whitespace()).peekable();
+
+    while let Some(letter) = chars.next() {
+        if !letter.is_ascii_alphabetic() {
+            return Err(GCodeError::BadWord(line_nr, letter.to_string()));
+        }
+
+        let mut value = String::new();
+
+        while let Some(&c) = chars.peek() {
+            if c.is_ascii_digit() || matches!(c, '.' | '-' | '+') {
+                value.push(c);
+                chars.next();
+            } else {
+                break;
+            }
+        }
+
+        let number = value.parse().map_err(|_| GCodeError::BadWord(line_nr, format!("{letter}{value}")))?;
+        words.push((letter, number));
+    }
+
+    Ok(words)
+}
+
+impl GCodeProgram {
+    /// Parses the supported G-code subset: G0/G1 (X/Y/Z/F), G4, G20/G21, G28, G90/G91 and M3/M5 for the pen
+    ///
+    /// The pen is lowered by M3 or a Z-position at or below `PEN_DOWN_Z_MAX` and lifted by M5 or a higher Z-position.
+    /// G1 moves draw while the pen is lowered, G0 moves always travel with the pen lifted. G28 moves the pen to the drawing origin.
+    ///
+    /// Dwell times follow the Marlin dialect: `G4 P` is given in milliseconds and `G4 S` in seconds.
+    pub fn parse(text : &str) -> Result<Self, GCodeError> {
+        let mut ops = Vec::new();
+        let mut state = ParserState {
+            pos: [ 0.0, 0.0, 0.0 ],
+            relative: false,
+            unit: 1.0,
+            rapid: true,
+            pen_down: false,
+            feed: None
+        };
+
+        for (index, raw_line) in text.lines().enumerate() {
+            let line_nr = index + 1;
+            let words = split_words(&strip_line(raw_line), line_nr)?;
+
+            let word = |letter : char| words.iter().find(|(l, _)| *l == letter).map(|(_, v)| *v);
+            let mut motion = false;
+            let mut dwell = false;
+
+            for &(letter, value) in &words {
+                let code = || format!("{letter}{value}");
+
+                match (letter, value as u32) {
+                    ('G', 0) => { state.rapid = true; motion = true },
+                    ('G', 1) => { state.rapid = false; motion = true },
+                    ('G', 4) => dwell = true,
+                    ('G', 20) => state.unit = 25.4,
+                    ('G', 21) => state.unit = 1.0,
+                    ('G', 28) => {
+                        // Homing ends above the drawing origin
+                        ops.push(GCodeOp::Home);
+                        state.pos = [ 0.0, 0.0, state.pos[2] ];
+                    },
+                    ('G', 90) => state.relative = false,
+                    ('G', 91) => state.relative = true,
+                    // Plane selection and feed modes have no effect on the drake
+                    ('G', 17 | 94) => { },
+                    ('G', _) => return Err(GCodeError::Unsupported(line_nr, code())),
+                    ('M', 3 | 4) => state.pen_down = true,
+                    ('M', 5) => state.pen_down = false,
+                    ('M', _) => log::warn!("Ignoring unsupported code '{}' in line {}", code(), line_nr),
+                    _ => { }
+                }
+            }
+
+            if dwell {
+                // P in milliseconds and S in seconds as in Marlin, Grbl and LinuxCNC read P in seconds
+                let secs = word('P').map(|ms| ms / 1000.0).or(word('S')).unwrap_or(0.0);
+                ops.push(GCodeOp::Dwell(Duration::from_secs_f32(secs.max(0.0))));
+                continue;
+            }
+
+            if let Some(feed) = word('F') {
+                // Feed rates are given in units per minute
+                state.feed = Some(feed * state.unit / 60.0);
+            }
+
+            let (x, y, z) = (word('X'), word('Y'), word('Z'));
+
+            if (x.is_none() && y.is_none() && z.is_none()) || (!motion && words.iter().any(|(l, _)| matches!(l, 'G' | 'M'))) {
+                continue;
+            }
+
+            let target = |current : f32, value : Option<f32>| match value {
+                Some(v) if state.relative => current + v * state.unit,
+                Some(v) => v * state.unit,
+                None => current
+            };
+
+            let new_pos = [ target(state.pos[0], x), target(state.pos[1], y), target(state.pos[2], z) ];
+
+            if z.is_some() {
+                state.pen_down = new_pos[2] <= PEN_DOWN_Z_MAX;
+            }
+
+            if (new_pos[0] != state.pos[0]) || (new_pos[1] != state.pos[1]) {
+                let to = [ new_pos[0], new_pos[1] ];
+
+                ops.push(if state.pen_down && !state.rapid {
+                    GCodeOp::Draw { to, feed: state.feed }
+                } else {
+                    GCodeOp::Travel(to)
+                });
+            }
+
+            state.pos = new_pos;
+        }
+
+        Ok(Self { ops })
+    }
+
+    pub fn load(path : &str) -> Result<Self, syact::Error> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// Collects all drawing moves into a drawing (mm), ignoring homing and dwell times
+    pub fn to_drawing(&self) -> Drawing {
+        let mut strokes : Vec<Polyline> = Vec::new();
+        let mut pos = [ 0.0, 0.0 ];
+        let mut stroke : Polyline = Vec::new();
+
+        for op in &self.ops {
+            match op {
+                GCodeOp::Travel(to) => {
+                    if stroke.len() > 1 {
+                        strokes.push(core::mem::take(&mut stroke));
+                    }
+
+                    stroke.clear();
+                    pos = *to;
+                },
+                GCodeOp::Draw { to, .. } => {
+                    if stroke.is_empty() {
+                        stroke.push(pos);
+                    }
+
+                    stroke.push(*to);
+                    pos = *to;
+                },
+                GCodeOp::Home => {
+                    if stroke.len() > 1 {
+                        strokes.push(core::mem::take(&mut stroke));
+                    }
+
+                    stroke.clear();
+                    pos = [ 0.0, 0.0 ];
+                },
+                GCodeOp::Dwell(_) => { }
+            }
+        }
+
+        if stroke.len() > 1 {
+            strokes.push(stroke);
+        }
+
+        Drawing {
+            strokes,
+            unit: DrawingUnit::Millimeter
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw(x : f32, y : f32, feed : Option<f32>) -> GCodeOp {
+        GCodeOp::Draw { to: [ x, y ], feed }
+    }
+
+    #[test]
+    fn absolute_and_relative_moves() {
+        let program = GCodeProgram::parse("G90\nM3\nG1 X10 Y0\nG91\nG1 X5 Y5\nG1 Y-5\nG90\nG1 X0 Y0").unwrap();
+
+        assert_eq!(program.ops, vec![
+            draw(10.0, 0.0, None),
+            draw(15.0, 5.0, None),
+            draw(15.0, 0.0, None),
+            draw(0.0, 0.0, None)
+        ]);
+    }
+
+    #[test]
+    fn units_and_feed_rates() {
+        let program = GCodeProgram::parse("G21 M3\nG1 X10 F600\nG20\nG1 X1 F60\nG1 Y1").unwrap();
+
+        // Feed rates are converted from units per minute into mm/s
+        assert_eq!(program.ops[0], draw(10.0, 0.0, Some(10.0)));
+
+        let GCodeOp::Draw { to, feed: Some(feed) } = program.ops[1] else {
+            panic!("{:?}", program.ops);
+        };
+        assert!((to[0] - 25.4).abs() < 1e-4);
+        assert!((feed - 25.4).abs() < 1e-4);
+
+        // The feed rate is modal
+        assert!(matches!(program.ops[2], GCodeOp::Draw { feed: Some(f), .. } if f == feed));
+    }
+
+    #[test]
+    fn pen_state() {
+        let program = GCodeProgram::parse(
+            "G1 X10\nM3\nG1 X20\nM5\nG1 X30\nG1 Z-1\nG1 X40\nG1 Z1\nG1 X50\nM3\nG0 X60 (rapid) ; comment"
+        ).unwrap();
+
+        assert_eq!(program.ops, vec![
+            GCodeOp::Travel([ 10.0, 0.0 ]),
+            // M3 lowers the pen, M5 lifts it
+            draw(20.0, 0.0, None),
+            GCodeOp::Travel([ 30.0, 0.0 ]),
+            // Z-positions at or below `PEN_DOWN_Z_MAX` lower the pen
+            draw(40.0, 0.0, None),
+            GCodeOp::Travel([ 50.0, 0.0 ]),
+            // G0 always travels
+            GCodeOp::Travel([ 60.0, 0.0 ])
+        ]);
+    }
+
+    #[test]
+    fn dwell_times() {
+        let program = GCodeProgram::parse("G4 P500\nG4 S2\nG4").unwrap();
+
+        assert_eq!(program.ops, vec![
+            GCodeOp::Dwell(Duration::from_millis(500)),
+            GCodeOp::Dwell(Duration::from_secs(2)),
+            GCodeOp::Dwell(Duration::ZERO)
+        ]);
+    }
+
+    #[test]
+    fn home_resets_position() {
+        let program = GCodeProgram::parse("M3\nG1 X10 Y10\nG28\nG91\nG1 X5").unwrap();
+
+        assert_eq!(program.ops, vec![ draw(10.0, 10.0, None), GCodeOp::Home, draw(5.0, 0.0, None) ]);
+
+        // Drawing continues from the origin after homing
+        assert_eq!(program.to_drawing().strokes, vec![
+            vec![ [ 0.0, 0.0 ], [ 10.0, 10.0 ] ],
+            vec![ [ 0.0, 0.0 ], [ 5.0, 0.0 ] ]
+        ]);
+    }
+
+    #[test]
+    fn malformed_words() {
+        assert!(matches!(GCodeProgram::parse("G1 X1.2.3"), Err(GCodeError::BadWord(1, w)) if w == "X1.2.3"));
+        assert!(matches!(GCodeProgram::parse("G1 X1\nG1 Y"), Err(GCodeError::BadWord(2, w)) if w == "Y"));
+        assert!(matches!(GCodeProgram::parse("10 X1"), Err(GCodeError::BadWord(1, w)) if w == "1"));
+        assert!(matches!(GCodeProgram::parse("G2 X1 Y1 I1"), Err(GCodeError::Unsupported(1, c)) if c == "G2"));
+    }
+}
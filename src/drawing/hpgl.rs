@@ -0,0 +1,209 @@
+use crate::drawing::{Drawing, DrawingUnit, Polyline};
+
+/// HPGL plotter units per millimeter (1 unit = 0.025mm)
+pub const HPGL_UNITS_PER_MM : f32 = 40.0;
+
+/// Terminator of label (LB) instructions
+const LABEL_TERMINATOR : char = '\u{3}';
+
+// Errors
+    #[derive(Debug, Clone)]
+    pub enum HpglError {
+        /// A parameter of the given instruction could not be parsed
+        BadParameter(String, String),
+        /// A pen move instruction with an odd amount of coordinates
+        OddCoordinates(String)
+    }
+
+    impl core::fmt::Display for HpglError {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            match self {
+                Self::BadParameter(instr, param) => f.write_fmt(format_args!("BadParameter: The parameter '{param}' of instruction '{instr}' is invalid!")),
+                Self::OddCoordinates(instr) => f.write_fmt(format_args!("OddCoordinates: The instruction '{instr}' has an odd amount of coordinates!"))
+            }
+        }
+    }
+
+    impl std::error::Error for HpglError { }
+//
+
+/// Pen state while interpreting a plot file
+struct Plotter {
+    pos : [f32; 2],
+    pen_down : bool,
+    relative : bool,
+    stroke : Polyline,
+    strokes : Vec<Polyline>
+}
+
+impl Plotter {
+    fn lift(&mut self) {
+        if self.stroke.len() > 1 {
+            self.strokes.push(core::mem::take(&mut self.stroke));
+        }
+
+        self.stroke.clear();
+        self.pen_down = false;
+    }
+
+    fn move_to(&mut self, value : [f32; 2]) {
+        let target = if self.relative {
+            [ self.pos[0] + value[0], self.pos[1] + value[1] ]
+        } else {
+            value
+        };
+
+        if self.pen_down {
+            if self.stroke.is_empty() {
+                self.stroke.push(self.pos);
+            }
+
+            self.stroke.push(target);
+        }
+
+        self.pos = target;
+    }
+}
+
+/// Splits a plot file into its instructions, e.g. `PU0,0;PD100,0` into `[("PU", "0,0"), ("PD", "100,0")]`
+fn split_instructions(text : &str) -> Vec<(String, String)> {
+    let mut instructions = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if !c.is_ascii_alphabetic() {
+            continue;
+        }
+
+        let Some(second) = chars.next() else {
+            break;
+        };
+
+        let mnemonic : String = [ c, second ].iter().collect::<String>().to_uppercase();
+        let mut params = String::new();
+
+        if mnemonic == "LB" {
+            // Labels may contain any character, skip them up to their terminator
+            for c in chars.by_ref() {
+                if c == LABEL_TERMINATOR {
+                    break;
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+
+                chars.next();
+
+                if c == ';' {
+                    break;
+                }
+
+                params.push(c);
+            }
+        }
+
+        instructions.push((mnemonic, params.trim().to_string()));
+    }
+
+    instructions
+}
+
+/// Parses the pen instructions of HPGL plot files (IN, PU, PD, PA, PR and SP) into a drawing in millimeters
+///
+/// All other instructions are ignored.
+pub fn parse_hpgl(text : &str) -> Result<Drawing, HpglError> {
+    let mut plotter = Plotter {
+        pos: [ 0.0, 0.0 ],
+        pen_down: false,
+        relative: false,
+        stroke: Vec::new(),
+        strokes: Vec::new()
+    };
+
+    for (mnemonic, params) in split_instructions(text) {
+        let values = params.split(|c : char| (c == ',') || c.is_whitespace())
+            .filter(|param| !param.is_empty())
+            .map(|param| param.parse::<f32>().map_err(|_| HpglError::BadParameter(mnemonic.clone(), param.to_string())))
+            .collect::<Result<Vec<f32>, HpglError>>()?;
+
+        match mnemonic.as_str() {
+            "IN" => {
+                plotter.lift();
+                plotter.pos = [ 0.0, 0.0 ];
+                plotter.relative = false;
+            },
+            "PU" => plotter.lift(),
+            "PD" => plotter.pen_down = true,
+            "PA" => plotter.relative = false,
+            "PR" => plotter.relative = true,
+            "SP" => {
+                // Selecting pen 0 stores the pen, every other pen is the one mounted on the drake
+                if values.first() == Some(&0.0) {
+                    plotter.lift();
+                }
+                continue;
+            },
+            _ => continue
+        }
+
+        if (values.len() % 2) == 1 {
+            return Err(HpglError::OddCoordinates(mnemonic));
+        }
+
+        for pair in values.chunks(2) {
+            plotter.move_to([ pair[0] / HPGL_UNITS_PER_MM, pair[1] / HPGL_UNITS_PER_MM ]);
+        }
+    }
+
+    plotter.lift();
+
+    Ok(Drawing {
+        strokes: plotter.strokes,
+        unit: DrawingUnit::Millimeter
+    })
+}
+
+pub fn load_hpgl(path : &str) -> Result<Drawing, syact::Error> {
+    Ok(parse_hpgl(&std::fs::read_to_string(path)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pen_up_and_down() {
+        let drawing = parse_hpgl("IN;SP1;PU400,400;PD800,400,800,800;PU;PD1200,800;PU0,0;SP0;").unwrap();
+
+        assert_eq!(drawing.strokes, vec![
+            vec![ [ 10.0, 10.0 ], [ 20.0, 10.0 ], [ 20.0, 20.0 ] ],
+            vec![ [ 20.0, 20.0 ], [ 30.0, 20.0 ] ]
+        ]);
+    }
+
+    #[test]
+    fn relative_moves() {
+        let drawing = parse_hpgl("PA400,0;PR;PD400,0 0,400;PU-400,0;PD0,-400").unwrap();
+
+        assert_eq!(drawing.strokes, vec![
+            vec![ [ 10.0, 0.0 ], [ 20.0, 0.0 ], [ 20.0, 10.0 ] ],
+            vec![ [ 10.0, 10.0 ], [ 10.0, 0.0 ] ]
+        ]);
+    }
+
+    #[test]
+    fn labels_and_unknown_instructions_are_skipped() {
+        let drawing = parse_hpgl("IN;LBPD;PU 1,2;\u{3};VS10;PD;PA40,40;PU;").unwrap();
+
+        assert_eq!(drawing.strokes, vec![ vec![ [ 0.0, 0.0 ], [ 1.0, 1.0 ] ] ]);
+    }
+
+    #[test]
+    fn odd_coordinates() {
+        assert!(matches!(parse_hpgl("PD100,100,200"), Err(HpglError::OddCoordinates(_))));
+        assert!(matches!(parse_hpgl("PD1.2.3,100"), Err(HpglError::BadParameter(_, _))));
+    }
+}
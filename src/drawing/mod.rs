@@ -0,0 +1,154 @@
+// Submodules
+    pub mod gcode;
+
+    pub mod hpgl;
+
+    pub mod optimise;
+
+    pub mod simplify;
+
+    pub mod stroke_font;
+
+    pub mod svg;
+
+    pub mod text;
+
+    pub mod transform;
+// 
+
+#[derive(Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Line {
+    pub p1 : [f32; 2],
+    pub p2 : [f32; 2]
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct LinesFile {
+    pub contour : Vec<Line>
+}
+
+pub fn load_points(path : &str) -> LinesFile {
+    serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+/// Distance between two points
+pub fn dist(p1 : [f32; 2], p2 : [f32; 2]) -> f32 {
+    ((p2[0] - p1[0]).powi(2) + (p2[1] - p1[1]).powi(2)).sqrt()
+}
+
+/// A chain of points that is drawn without lifting the pen
+pub type Polyline = Vec<[f32; 2]>;
+
+/// Unit of the coordinates of a drawing
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DrawingUnit {
+    /// Image pixels, scaled with the `pixel_per_mm` value of the config
+    #[default]
+    Pixel,
+    Millimeter
+}
+
+/// A drawing made out of strokes, which are drawn in the given order
+#[derive(Clone, Debug, Default)]
+pub struct Drawing {
+    pub strokes : Vec<Polyline>,
+    pub unit : DrawingUnit
+}
+
+impl Drawing {
+    /// Creates a drawing with one stroke per line
+    pub fn from_lines(lines : &LinesFile) -> Self {
+        Self {
+            strokes: lines.contour.iter().map(|line| vec![ line.p1, line.p2 ]).collect(),
+            unit: DrawingUnit::Pixel
+        }
+    }
+
+    /// Loads a drawing, choosing the format by the file extension (`.svg`, HPGL, G-code or a JSON lines file)
+    /// 
+    /// Curves are flattened within the given tolerance (mm)
+    pub fn load(path : &str, tolerance : f32) -> Result<Self, syact::Error> {
+        let extension = std::path::Path::new(path).extension()
+            .and_then(|ext| ext.to_str()).unwrap_or("").to_lowercase();
+
+        match extension.as_str() {
+            "svg" => svg::load_svg(path, tolerance),
+            "hpgl" | "plt" | "hpg" => hpgl::load_hpgl(path),
+            ext if gcode::GCODE_EXTENSIONS.contains(&ext) => Ok(gcode::GCodeProgram::load(path)?.to_drawing()),
+            _ => Ok(Self::from_lines(&serde_json::from_str(&std::fs::read_to_string(path)?)?))
+        }
+    }
+
+    /// Splits all strokes back into single lines
+    pub fn to_lines(&self) -> LinesFile {
+        LinesFile {
+            contour: self.lines().map(|(p1, p2)| Line { p1, p2 }).collect()
+        }
+    }
+
+    /// Iterates over all lines of all strokes
+    pub fn lines(&self) -> impl Iterator<Item = ([f32; 2], [f32; 2])> + '_ {
+        self.strokes.iter().flat_map(|stroke| stroke.windows(2).map(|w| (w[0], w[1])))
+    }
+
+    /// Total amount of lines in all strokes
+    pub fn line_count(&self) -> usize {
+        self.strokes.iter().map(|stroke| stroke.len().saturating_sub(1)).sum()
+    }
+
+    /// The drawing without its first `count` lines, splitting the stroke the remaining lines start in
+    pub fn skip_lines(&self, count : usize) -> Self {
+        let mut remaining = count;
+        let mut strokes = Vec::new();
+
+        for stroke in &self.strokes {
+            let lines = stroke.len().saturating_sub(1);
+
+            if remaining == 0 {
+                strokes.push(stroke.clone());
+            } else if remaining < lines {
+                strokes.push(stroke[remaining..].to_vec());
+                remaining = 0;
+            } else {
+                remaining -= lines;
+            }
+        }
+
+        Self {
+            strokes,
+            unit: self.unit
+        }
+    }
+
+    /// Amount of times the pen has to be lifted, including the initial positioning
+    pub fn pen_lifts(&self) -> usize {
+        let mut last_point : Option<[f32; 2]> = None;
+        let mut lifts = 0;
+
+        for stroke in self.strokes.iter().filter(|stroke| !stroke.is_empty()) {
+            if last_point != Some(stroke[0]) {
+                lifts += 1;
+            }
+
+            last_point = stroke.last().copied();
+        }
+
+        lifts
+    }
+
+    /// Distance traveled with the pen lifted, starting at the first stroke
+    pub fn travel_distance(&self) -> f32 {
+        let mut last_point : Option<[f32; 2]> = None;
+        let mut distance = 0.0;
+
+        for stroke in self.strokes.iter().filter(|stroke| !stroke.is_empty()) {
+            if let Some(last) = last_point {
+                distance += dist(last, stroke[0]);
+            }
+
+            last_point = stroke.last().copied();
+        }
+
+        distance
+    }
+}
@@ -0,0 +1,321 @@
+use core::fmt::Display;
+use std::collections::HashMap;
+
+use crate::drawing::{dist, Drawing, Polyline};
+
+/// Endpoints closer than this distance (in drawing units) are considered touching
+pub const MERGE_TOLERANCE : f32 = 0.01;
+/// Maximum amount of improvement passes of the 2-opt heuristic
+pub const TWO_OPT_PASSES : usize = 32;
+
+/// Pen lift and travel statistics before and after an optimisation
+#[derive(Clone, Debug)]
+pub struct OptimiseReport {
+    pub strokes_before : usize,
+    pub strokes_after : usize,
+    pub lifts_before : usize,
+    pub lifts_after : usize,
+    pub travel_before : f32,
+    pub travel_after : f32
+}
+
+impl Display for OptimiseReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_fmt(format_args!(
+            "strokes: {} -> {}, pen lifts: {} -> {}, travel: {:.1} -> {:.1}",
+            self.strokes_before, self.strokes_after,
+            self.lifts_before, self.lifts_after,
+            self.travel_before, self.travel_after
+        ))
+    }
+}
+
+// Merging
+    fn grid_key(point : [f32; 2]) -> (i64, i64) {
+        ((point[0] / MERGE_TOLERANCE).round() as i64, (point[1] / MERGE_TOLERANCE).round() as i64)
+    }
+
+    /// An edge of the stroke graph, either a stroke of the drawing or a virtual connection between two odd vertices
+    struct Edge {
+        from : usize,
+        to : usize,
+        stroke : Option<Polyline>
+    }
+
+    fn find_root(parents : &mut [usize], mut index : usize) -> usize {
+        while parents[index] != index {
+            parents[index] = parents[parents[index]];
+            index = parents[index];
+        }
+
+        index
+    }
+
+    /// Joins strokes with touching endpoints into the fewest possible polylines, reversing them where required
+    /// 
+    /// The strokes are treated as edges of a graph: odd vertices of every connected part are paired up with virtual edges,
+    /// an euler circuit is searched (Hierholzer) and split up again at the virtual edges.
+    pub fn merge_strokes(strokes : Vec<Polyline>) -> Vec<Polyline> {
+        // Build the graph
+        let mut vertex_ids : HashMap<(i64, i64), usize> = HashMap::new();
+        let mut edges : Vec<Edge> = Vec::new();
+
+        for stroke in strokes.into_iter().filter(|stroke| stroke.len() > 1) {
+            let mut vertex = |point : [f32; 2]| {
+                let next_id = vertex_ids.len();
+                *vertex_ids.entry(grid_key(point)).or_insert(next_id)
+            };
+
+            let from = vertex(stroke[0]);
+            let to = vertex(stroke[stroke.len() - 1]);
+
+            edges.push(Edge { from, to, stroke: Some(stroke) });
+        }
+
+        let vertex_count = vertex_ids.len();
+
+        // Connected parts and vertex degrees
+        let mut parents : Vec<usize> = (0 .. vertex_count).collect();
+        let mut degrees = vec![0usize; vertex_count];
+
+        for edge in &edges {
+            let (root_from, root_to) = (find_root(&mut parents, edge.from), find_root(&mut parents, edge.to));
+            parents[root_from] = root_to;
+
+            degrees[edge.from] += 1;
+            degrees[edge.to] += 1;
+        }
+
+        // Pair up the odd vertices of every connected part
+        let mut odd_vertices : HashMap<usize, Vec<usize>> = HashMap::new();
+
+        for vertex in (0 .. vertex_count).filter(|&v| (degrees[v] % 2) == 1) {
+            odd_vertices.entry(find_root(&mut parents, vertex)).or_default().push(vertex);
+        }
+
+        for vertices in odd_vertices.values() {
+            for pair in vertices.chunks(2) {
+                edges.push(Edge { from: pair[0], to: pair[1], stroke: None });
+            }
+        }
+
+        let mut adjacent : Vec<Vec<(usize, usize)>> = vec![Vec::new(); vertex_count];
+
+        for (index, edge) in edges.iter().enumerate() {
+            adjacent[edge.from].push((index, edge.to));
+            adjacent[edge.to].push((index, edge.from));
+        }
+
+        // Search an euler circuit for every connected part
+        let mut used = vec![false; edges.len()];
+        let mut next_adjacent = vec![0usize; vertex_count];
+        let mut merged = Vec::new();
+
+        for start in 0 .. vertex_count {
+            let mut stack : Vec<(usize, Option<(usize, bool)>)> = vec![ (start, None) ];
+            let mut circuit : Vec<(usize, bool)> = Vec::new();
+
+            while let Some(&(vertex, _)) = stack.last() {
+                while (next_adjacent[vertex] < adjacent[vertex].len()) && used[adjacent[vertex][next_adjacent[vertex]].0] {
+                    next_adjacent[vertex] += 1;
+                }
+
+                if let Some(&(edge, other)) = adjacent[vertex].get(next_adjacent[vertex]) {
+                    used[edge] = true;
+                    stack.push((other, Some((edge, edges[edge].from == vertex))));
+                } else if let Some((_, Some(step))) = stack.pop() {
+                    circuit.push(step);
+                }
+            }
+
+            if circuit.is_empty() {
+                continue;
+            }
+
+            circuit.reverse();
+
+            // Start right after a virtual edge, so the circuit only has to be split at the virtual edges
+            if let Some(index) = circuit.iter().position(|&(edge, _)| edges[edge].stroke.is_none()) {
+                circuit.rotate_left(index + 1);
+            }
+
+            let mut polyline : Polyline = Vec::new();
+
+            for (edge, forward) in circuit {
+                let Some(stroke) = &edges[edge].stroke else {
+                    if !polyline.is_empty() {
+                        merged.push(core::mem::take(&mut polyline));
+                    }
+                    continue;
+                };
+
+                // Skip the joint point, it is already part of the polyline
+                let skip = if polyline.is_empty() { 0 } else { 1 };
+
+                if forward {
+                    polyline.extend(stroke.iter().skip(skip));
+                } else {
+                    polyline.extend(stroke.iter().rev().skip(skip));
+                }
+            }
+
+            if !polyline.is_empty() {
+                merged.push(polyline);
+            }
+        }
+
+        merged
+    }
+//
+
+// Ordering
+    /// Orders the strokes greedily, always continuing with the stroke whose start or end is closest to the pen
+    pub fn order_nearest_neighbour(mut strokes : Vec<Polyline>, start : [f32; 2]) -> Vec<Polyline> {
+        let mut ordered = Vec::with_capacity(strokes.len());
+        let mut pos = start;
+
+        while !strokes.is_empty() {
+            let mut best = (0, false, f32::INFINITY);
+
+            for (index, stroke) in strokes.iter().enumerate() {
+                let dist_start = dist(pos, stroke[0]);
+                let dist_end = dist(pos, stroke[stroke.len() - 1]);
+
+                if dist_start < best.2 {
+                    best = (index, false, dist_start);
+                }
+
+                if dist_end < best.2 {
+                    best = (index, true, dist_end);
+                }
+            }
+
+            let mut stroke = strokes.swap_remove(best.0);
+            if best.1 {
+                stroke.reverse();
+            }
+
+            pos = stroke[stroke.len() - 1];
+            ordered.push(stroke);
+        }
+
+        ordered
+    }
+
+    /// Improves the order of the strokes by reversing sections of it (including the direction of every stroke in the section),
+    /// as long as that shortens the travel distance
+    pub fn improve_two_opt(strokes : &mut [Polyline], start : [f32; 2], max_passes : usize) {
+        let first = |stroke : &Polyline| stroke[0];
+        let last = |stroke : &Polyline| stroke[stroke.len() - 1];
+
+        for _ in 0 .. max_passes {
+            let mut improved = false;
+
+            for i in 0 .. strokes.len() {
+                let prev_end = if i == 0 { start } else { last(&strokes[i - 1]) };
+
+                for j in (i + 1) .. strokes.len() {
+                    let before = dist(prev_end, first(&strokes[i]))
+                        + strokes.get(j + 1).map(|next| dist(last(&strokes[j]), first(next))).unwrap_or(0.0);
+                    let after = dist(prev_end, last(&strokes[j]))
+                        + strokes.get(j + 1).map(|next| dist(first(&strokes[i]), first(next))).unwrap_or(0.0);
+
+                    if after < (before - MERGE_TOLERANCE) {
+                        strokes[i ..= j].reverse();
+
+                        for stroke in &mut strokes[i ..= j] {
+                            stroke.reverse();
+                        }
+
+                        improved = true;
+                    }
+                }
+            }
+
+            if !improved {
+                break;
+            }
+        }
+    }
+//
+
+/// Merges touching strokes and reorders them to reduce pen lifts and travel, starting at the drawing origin
+pub fn optimise(drawing : &Drawing) -> (Drawing, OptimiseReport) {
+    let mut strokes = order_nearest_neighbour(merge_strokes(drawing.strokes.clone()), [ 0.0, 0.0 ]);
+    improve_two_opt(&mut strokes, [ 0.0, 0.0 ], TWO_OPT_PASSES);
+
+    let optimised = Drawing { strokes, unit: drawing.unit };
+
+    let report = OptimiseReport {
+        strokes_before: drawing.strokes.len(),
+        strokes_after: optimised.strokes.len(),
+        lifts_before: drawing.pen_lifts(),
+        lifts_after: optimised.pen_lifts(),
+        travel_before: drawing.travel_distance(),
+        travel_after: optimised.travel_distance()
+    };
+
+    (optimised, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// All segments of the strokes, independent of their direction and order
+    fn segments(strokes : &[Polyline]) -> Vec<((i64, i64), (i64, i64))> {
+        let mut segments : Vec<_> = strokes.iter()
+            .flat_map(|stroke| stroke.windows(2))
+            .map(|w| {
+                let (a, b) = (grid_key(w[0]), grid_key(w[1]));
+                if a <= b { (a, b) } else { (b, a) }
+            }).collect();
+
+        segments.sort();
+        segments
+    }
+
+    fn strokes() -> Vec<Polyline> {
+        vec![
+            // A square split into three strokes, one of them reversed
+            vec![ [ 0.0, 0.0 ], [ 10.0, 0.0 ] ],
+            vec![ [ 10.0, 10.0 ], [ 10.0, 0.0 ] ],
+            vec![ [ 10.0, 10.0 ], [ 0.0, 10.0 ], [ 0.0, 0.0 ] ],
+            // A cross with a shared center
+            vec![ [ 20.0, 5.0 ], [ 25.0, 5.0 ] ],
+            vec![ [ 25.0, 5.0 ], [ 30.0, 5.0 ] ],
+            vec![ [ 25.0, 0.0 ], [ 25.0, 5.0 ] ],
+            vec![ [ 25.0, 5.0 ], [ 25.0, 10.0 ] ],
+            // A lone stroke far away
+            vec![ [ 50.0, 50.0 ], [ 55.0, 52.0 ], [ 60.0, 50.0 ] ]
+        ]
+    }
+
+    #[test]
+    fn merge_keeps_segments() {
+        let merged = merge_strokes(strokes());
+
+        assert_eq!(segments(&merged), segments(&strokes()));
+        // Square, cross (two odd vertex pairs) and the lone stroke
+        assert_eq!(merged.len(), 4);
+    }
+
+    #[test]
+    fn two_opt_keeps_segments() {
+        let mut ordered = strokes();
+        ordered.reverse();
+        improve_two_opt(&mut ordered, [ 0.0, 0.0 ], TWO_OPT_PASSES);
+
+        assert_eq!(segments(&ordered), segments(&strokes()));
+    }
+
+    #[test]
+    fn optimise_keeps_segments() {
+        let drawing = Drawing { strokes: strokes(), unit: crate::drawing::DrawingUnit::Millimeter };
+        let (optimised, report) = optimise(&drawing);
+
+        assert_eq!(segments(&optimised.strokes), segments(&drawing.strokes));
+        assert!(report.lifts_after <= report.lifts_before);
+        assert!(report.travel_after <= report.travel_before);
+    }
+}
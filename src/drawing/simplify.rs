@@ -0,0 +1,92 @@
+use crate::drawing::{dist, Drawing, Polyline};
+
+/// Distance of a point to the line segment between `start` and `end`
+pub fn dist_to_segment(point : [f32; 2], start : [f32; 2], end : [f32; 2]) -> f32 {
+    let seg = [ end[0] - start[0], end[1] - start[1] ];
+    let len_sq = seg[0] * seg[0] + seg[1] * seg[1];
+
+    if len_sq == 0.0 {
+        return dist(point, start);
+    }
+
+    let t = (((point[0] - start[0]) * seg[0] + (point[1] - start[1]) * seg[1]) / len_sq).clamp(0.0, 1.0);
+    dist(point, [ start[0] + t * seg[0], start[1] + t * seg[1] ])
+}
+
+/// Reduces the polyline to the fewest points that keep all removed points within the given tolerance (Ramer–Douglas–Peucker)
+pub fn simplify_polyline(points : &[[f32; 2]], tolerance : f32) -> Polyline {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    // Sections still to check, iterative to support polylines with thousands of points
+    let mut sections = vec![ (0, points.len() - 1) ];
+
+    while let Some((first, last)) = sections.pop() {
+        let mut max_dist = 0.0;
+        let mut max_index = first;
+
+        for index in (first + 1) .. last {
+            let d = dist_to_segment(points[index], points[first], points[last]);
+
+            if d > max_dist {
+                max_dist = d;
+                max_index = index;
+            }
+        }
+
+        if max_dist > tolerance {
+            keep[max_index] = true;
+            sections.push((first, max_index));
+            sections.push((max_index, last));
+        }
+    }
+
+    points.iter().zip(keep).filter(|(_, keep)| *keep).map(|(&p, _)| p).collect()
+}
+
+/// Simplifies every stroke of the drawing with the given tolerance (in drawing units)
+pub fn simplify(drawing : &Drawing, tolerance : f32) -> Drawing {
+    Drawing {
+        strokes: drawing.strokes.iter().map(|stroke| simplify_polyline(stroke, tolerance)).collect(),
+        unit: drawing.unit
+    }
+}
+
+/// Simplifies every stroke of a drawing with the given amount of units per millimeter, the tolerance is given in millimeters
+pub fn simplify_mm(drawing : &Drawing, tolerance_mm : f32, units_per_mm : f32) -> Drawing {
+    simplify(drawing, tolerance_mm * units_per_mm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_endpoints() {
+        let points : Polyline = (0 ..= 20).map(|i| [ i as f32, (i as f32 * 0.7).sin() * 0.01 ]).collect();
+        let simplified = simplify_polyline(&points, 0.1);
+
+        assert_eq!(simplified, vec![ points[0], points[20] ]);
+    }
+
+    #[test]
+    fn keeps_corners() {
+        let points = vec![ [ 0.0, 0.0 ], [ 5.0, 0.01 ], [ 10.0, 0.0 ], [ 10.0, 10.0 ], [ 10.0, 20.0 ] ];
+        let simplified = simplify_polyline(&points, 0.1);
+
+        assert_eq!(simplified, vec![ [ 0.0, 0.0 ], [ 10.0, 0.0 ], [ 10.0, 20.0 ] ]);
+    }
+
+    #[test]
+    fn keeps_closed_strokes() {
+        let square = vec![ [ 0.0, 0.0 ], [ 10.0, 0.0 ], [ 10.0, 10.0 ], [ 0.0, 10.0 ], [ 0.0, 0.0 ] ];
+        let simplified = simplify_polyline(&square, 0.1);
+
+        assert_eq!(simplified, square);
+    }
+}
@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+
+use crate::drawing::{Drawing, DrawingUnit, Polyline};
+use crate::drawing::text::{TextFont, TextStyle};
+
+/// Name of the built-in single-stroke font
+pub const SIMPLEX_NAME : &str = "simplex";
+
+/// Height of the em square in glyph units, the capital letters are 10 units high
+pub const UNITS_PER_EM : f32 = 14.0;
+/// Distance of the top of a line to its baseline (glyph units)
+pub const ASCENDER : f32 = 11.0;
+/// Distance between two baselines (glyph units)
+pub const LINE_HEIGHT : f32 = 16.0;
+
+/// Character drawn for all characters missing in the font
+const REPLACEMENT_CHAR : char = '?';
+
+// Glyph table
+    /// Hershey-style single-stroke glyphs as `(char, advance, strokes)`, in glyph units with Y up and the baseline at 0
+    ///
+    /// Strokes are separated by `;`, every stroke is a list of `x y` coordinates.
+    const SIMPLEX_GLYPHS : &[(char, f32, &str)] = &[
+        (' ', 5.0, ""),
+        ('!', 3.0, "1.5 10 1.5 2.8;1.5 0 1.5 0.6"),
+        ('"', 5.0, "1.5 10 1.5 7.5;3.5 10 3.5 7.5"),
+        ('#', 8.0, "3 10 2 0;6 10 5 0;1 6.5 7.5 6.5;0.5 3.5 7 3.5"),
+        ('$', 8.0, "7 8.5 6 9.7 4 10 2 9.7 1 8.5 1.2 6.8 2.5 5.8 5.5 4.3 6.8 3.3 7 1.7 6 0.3 4 0 2 0.3 1 1.5;4 11 4 -1"),
+        ('%', 8.0, "1 0 7 10;2 10 1 9 2 8 3 9 2 10;6 2 5 1 6 0 7 1 6 2"),
+        ('&', 8.0, "7 0 2 7 2 8.8 3 9.8 4.2 9.8 5 8.8 5 7.5 1.2 4 1 2 1.8 0.6 3.3 0 4.8 0.4 6.8 3"),
+        ('\'', 3.0, "1.5 10 1.5 7.5"),
+        ('(', 4.5, "3.5 11 2.2 9.5 1.3 7.3 1 5 1.3 2.7 2.2 0.5 3.5 -1"),
+        (')', 4.5, "1 11 2.3 9.5 3.2 7.3 3.5 5 3.2 2.7 2.3 0.5 1 -1"),
+        ('*', 8.0, "4 8 4 2;1.4 6.5 6.6 3.5;1.4 3.5 6.6 6.5"),
+        ('+', 8.0, "1 4 7 4;4 1 4 7"),
+        (',', 3.0, "1.8 0.6 1.8 0 1 -1.8"),
+        ('-', 6.0, "1 4 5 4"),
+        ('.', 3.0, "1.5 0 1.5 0.6"),
+        ('/', 6.0, "0.5 -1 5.5 11"),
+        ('0', 8.0, "4 10 2.5 9.4 1.5 7.8 1 5 1.5 2.2 2.5 0.6 4 0 5.5 0.6 6.5 2.2 7 5 6.5 7.8 5.5 9.4 4 10"),
+        ('1', 8.0, "2 8 4 10 4 0;2 0 6 0"),
+        ('2', 8.0, "1.2 8.3 2 9.5 3.5 10 5 10 6.3 9.2 6.8 7.8 6.4 6.2 1 0 7 0"),
+        ('3', 8.0, "1.2 9 2.5 9.9 4 10 5.5 9.7 6.5 8.7 6.6 7.3 5.8 6 4 5.3 2.8 5.3;4 5.3 5.8 4.8 6.9 3.5 6.9 2 6 0.6 4.3 0 2.5 0.1 1 1"),
+        ('4', 8.0, "5.5 0 5.5 10 1 2.8 7.2 2.8"),
+        ('5', 8.0, "6.5 10 1.8 10 1.3 5.5 2.8 6.2 4.2 6.3 5.8 5.8 6.8 4.5 7 3 6.6 1.3 5.3 0.2 4 0 2.5 0.1 1 1"),
+        ('6', 8.0, "6.3 9.3 5 10 3.5 9.8 2.2 8.8 1.3 7 1 4.5 1.1 2.3 2 0.7 3.5 0 5 0.1 6.3 1 7 2.6 6.7 4.4 5.6 5.6 4 6 2.6 5.6 1.5 4.5 1.1 3.2"),
+        ('7', 8.0, "1 10 7 10 3 0"),
+        ('8', 8.0, "4 5.4 2.4 6.2 1.6 7.5 1.9 9 3 9.8 4 10 5 9.8 6.1 9 6.4 7.5 5.6 6.2 4 5.4 2.1 4.6 1 3.2 1 1.8 1.9 0.6 4 0 6.1 0.6 7 1.8 7 3.2 5.9 4.6 4 5.4"),
+        ('9', 8.0, "6.9 6.8 6.5 5.5 5.4 4.4 4 4 2.4 4.4 1.3 5.6 1 7.2 1.4 8.8 2.6 9.8 4 10 5.4 9.6 6.5 8.4 7 6.5 6.9 4 6.3 1.8 5 0.3 3.5 0 2 0.3 1.2 1"),
+        (':', 3.0, "1.5 0 1.5 0.6;1.5 5.4 1.5 6"),
+        (';', 3.0, "1.8 5.4 1.8 6;1.8 0.6 1.8 0 1 -1.8"),
+        ('<', 8.0, "7 8 1 5 7 2"),
+        ('=', 8.0, "1 5.5 7 5.5;1 2.5 7 2.5"),
+        ('>', 8.0, "1 8 7 5 1 2"),
+        ('?', 7.0, "1 8.5 1.8 9.6 3.3 10 4.8 9.6 5.8 8.5 5.8 7.2 4.8 6 3.3 5.2 3.3 2.8;3.3 0 3.3 0.6"),
+        ('@', 9.0, "6 3 6 6 4.5 6.5 3.2 6 2.8 4.5 3.2 3.2 4.4 2.6 6 3 7.2 3 8 4.5 8 6.5 7.2 8.5 5.5 9.5 3.5 9.5 1.8 8.5 1 6.5 1 3.5 1.8 1.5 3.5 0.3 5.5 0.3 7 1"),
+        ('A', 8.0, "1 0 4 10 7 0;2.2 4 5.8 4"),
+        ('B', 8.5, "1 0 1 10 5 10 6.5 9.3 7 8 6.5 6.7 5 5.5 1 5.5;5 5.5 6.5 4.8 7 3.5 7 2 6.3 0.6 5 0 1 0"),
+        ('C', 8.0, "7 8.5 6 9.7 4.5 10 3 9.7 1.8 8.7 1 7 1 3 1.8 1.3 3 0.3 4.5 0 6 0.3 7 1.5"),
+        ('D', 8.5, "1 0 1 10 4 10 5.8 9.3 6.8 7.8 7.2 5 6.8 2.2 5.8 0.7 4 0 1 0"),
+        ('E', 8.0, "7 10 1 10 1 0 7 0;1 5 5.5 5"),
+        ('F', 7.5, "7 10 1 10 1 0;1 5 5.5 5"),
+        ('G', 8.5, "7 8.5 6 9.7 4.5 10 3 9.7 1.8 8.7 1 7 1 3 1.8 1.3 3 0.3 4.5 0 6 0.3 7 1.5 7 4.5 4.5 4.5"),
+        ('H', 8.0, "1 0 1 10;7 0 7 10;1 5 7 5"),
+        ('I', 6.0, "1 10 5 10;3 10 3 0;1 0 5 0"),
+        ('J', 7.5, "6 10 6 2.5 5.3 0.7 4 0 2.5 0 1.2 0.8 1 2"),
+        ('K', 8.0, "1 0 1 10;7 10 1 3.5;3.2 5.5 7 0"),
+        ('L', 7.5, "1 10 1 0 6.5 0"),
+        ('M', 10.0, "1 0 1 10 5 3 9 10 9 0"),
+        ('N', 8.0, "1 0 1 10 7 0 7 10"),
+        ('O', 8.0, "4 10 2.4 9.4 1.4 8 1 5 1.4 2 2.4 0.6 4 0 5.6 0.6 6.6 2 7 5 6.6 8 5.6 9.4 4 10"),
+        ('P', 8.0, "1 0 1 10 5 10 6.5 9.3 7 7.8 6.5 6.2 5 5.5 1 5.5"),
+        ('Q', 8.0, "4 10 2.4 9.4 1.4 8 1 5 1.4 2 2.4 0.6 4 0 5.6 0.6 6.6 2 7 5 6.6 8 5.6 9.4 4 10;4.5 2.5 7.5 -0.5"),
+        ('R', 8.0, "1 0 1 10 5 10 6.5 9.3 7 7.8 6.5 6.2 5 5.5 1 5.5;4.5 5.5 7 0"),
+        ('S', 8.0, "7 8.5 6 9.7 4 10 2 9.7 1 8.5 1.2 6.8 2.5 5.8 5.5 4.3 6.8 3.3 7 1.7 6 0.3 4 0 2 0.3 1 1.5"),
+        ('T', 8.0, "0.5 10 7.5 10;4 10 4 0"),
+        ('U', 8.0, "1 10 1 3 1.6 1.1 2.8 0.2 4 0 5.2 0.2 6.4 1.1 7 3 7 10"),
+        ('V', 8.0, "0.5 10 4 0 7.5 10"),
+        ('W', 10.0, "0.5 10 2.8 0 5 7.5 7.2 0 9.5 10"),
+        ('X', 8.0, "1 10 7 0;7 10 1 0"),
+        ('Y', 8.0, "0.5 10 4 5 7.5 10;4 5 4 0"),
+        ('Z', 8.0, "1 10 7 10 1 0 7 0"),
+        ('[', 4.5, "3.5 11 1 11 1 -1 3.5 -1"),
+        ('\\', 6.0, "0.5 11 5.5 -1"),
+        (']', 4.5, "1 11 3.5 11 3.5 -1 1 -1"),
+        ('^', 8.0, "1.5 7 4 10 6.5 7"),
+        ('_', 8.0, "0 -1.5 8 -1.5"),
+        ('`', 4.0, "1.5 10 2.5 8.5"),
+        ('a', 7.5, "6 6 6 0;6 4.5 5 5.7 3.5 6 2 5.6 1.2 4.5 1 3 1.2 1.5 2 0.4 3.5 0 5 0.3 6 1.5"),
+        ('b', 7.5, "1 10 1 0;1 4.5 2 5.7 3.5 6 5 5.6 5.8 4.5 6 3 5.8 1.5 5 0.4 3.5 0 2 0.3 1 1.5"),
+        ('c', 7.0, "6 4.8 5 5.8 3.5 6 2 5.6 1.2 4.5 1 3 1.2 1.5 2 0.4 3.5 0 5 0.2 6 1.2"),
+        ('d', 7.5, "6 10 6 0;6 4.5 5 5.7 3.5 6 2 5.6 1.2 4.5 1 3 1.2 1.5 2 0.4 3.5 0 5 0.3 6 1.5"),
+        ('e', 7.0, "1 3 6 3 5.8 4.5 5 5.6 3.5 6 2 5.6 1.2 4.5 1 3 1.2 1.5 2 0.4 3.5 0 5 0.2 6 1.2"),
+        ('f', 5.0, "5 10 3.8 10 2.8 9.3 2.5 8 2.5 0;1 6 4.5 6"),
+        ('g', 7.5, "6 6 6 -1 5.6 -2.3 4.5 -3 3 -3 1.8 -2.5;6 4.5 5 5.7 3.5 6 2 5.6 1.2 4.5 1 3 1.2 1.5 2 0.4 3.5 0 5 0.3 6 1.5"),
+        ('h', 7.5, "1 10 1 0;1 4.5 2 5.6 3.5 6 5 5.6 5.8 4.5 6 3 6 0"),
+        ('i', 3.0, "1.5 6 1.5 0;1.5 8.5 1.5 8"),
+        ('j', 4.0, "2.5 6 2.5 -1.5 2 -2.6 0.8 -3;2.5 8.5 2.5 8"),
+        ('k', 6.5, "1 10 1 0;5.5 6 1 2.2;2.6 3.5 5.8 0"),
+        ('l', 3.5, "1.5 10 1.5 1 2 0.2 2.8 0"),
+        ('m', 9.0, "1 6 1 0;1 4.5 1.8 5.6 3 6 4 5.6 4.5 4.5 4.5 0;4.5 4.5 5.3 5.6 6.5 6 7.5 5.6 8 4.5 8 0"),
+        ('n', 7.5, "1 6 1 0;1 4.5 2 5.6 3.5 6 5 5.6 5.8 4.5 6 3 6 0"),
+        ('o', 7.0, "3.5 6 2 5.6 1.2 4.5 1 3 1.2 1.5 2 0.4 3.5 0 5 0.4 5.8 1.5 6 3 5.8 4.5 5 5.6 3.5 6"),
+        ('p', 7.5, "1 6 1 -3;1 4.5 2 5.7 3.5 6 5 5.6 5.8 4.5 6 3 5.8 1.5 5 0.4 3.5 0 2 0.3 1 1.5"),
+        ('q', 7.5, "6 6 6 -3;6 4.5 5 5.7 3.5 6 2 5.6 1.2 4.5 1 3 1.2 1.5 2 0.4 3.5 0 5 0.3 6 1.5"),
+        ('r', 5.0, "1 6 1 0;1 3.5 1.8 5 3 5.8 4.5 6"),
+        ('s', 6.5, "5.5 5 4.5 5.8 3 6 1.7 5.7 1 4.8 1.3 3.8 2.5 3.2 4.2 2.8 5.3 2.2 5.5 1.2 4.8 0.3 3.3 0 1.8 0.2 1 1"),
+        ('t', 5.5, "2.5 9 2.5 1.2 3 0.3 4 0 4.8 0.2;1 6 4.5 6"),
+        ('u', 7.5, "1 6 1 3 1.2 1.5 2 0.4 3.5 0 5 0.4 6 1.5;6 6 6 0"),
+        ('v', 7.0, "1 6 3.5 0 6 6"),
+        ('w', 8.0, "0.5 6 2.2 0 4 5 5.8 0 7.5 6"),
+        ('x', 7.0, "1 6 6 0;6 6 1 0"),
+        ('y', 7.0, "1 6 3.5 0;6 6 3.5 0 2.5 -2.3 1.5 -3 0.8 -3"),
+        ('z', 7.0, "1 6 6 6 1 0 6 0"),
+        ('{', 5.0, "4 11 3 10.5 2.5 9.5 2.5 6 1 5 2.5 4 2.5 0.5 3 -0.5 4 -1"),
+        ('|', 4.0, "2 11 2 -1"),
+        ('}', 5.0, "1 11 2 10.5 2.5 9.5 2.5 6 4 5 2.5 4 2.5 0.5 2 -0.5 1 -1"),
+        ('~', 8.0, "1 4.5 2 5.5 3 5.5 5 4.5 6 4.5 7 5.5")
+    ];
+
+    /// Kerning pairs as `(left, right, adjustment)` in glyph units
+    const SIMPLEX_KERNING : &[(char, char, f32)] = &[
+        ('A', 'T', -0.8), ('A', 'V', -1.0), ('A', 'W', -0.8), ('A', 'Y', -1.0),
+        ('F', 'a', -0.8), ('F', 'o', -0.8), ('F', '.', -1.2), ('F', ',', -1.2),
+        ('L', 'T', -1.0), ('L', 'V', -1.0), ('L', 'W', -0.8), ('L', 'Y', -1.0),
+        ('P', 'A', -0.8), ('P', '.', -1.2), ('P', ',', -1.2),
+        ('T', 'A', -0.8), ('T', 'a', -1.2), ('T', 'e', -1.2), ('T', 'o', -1.2), ('T', 'r', -0.8), ('T', 'u', -0.8), ('T', 'y', -0.8),
+        ('T', '.', -1.2), ('T', ',', -1.2),
+        ('V', 'A', -1.0), ('V', 'a', -0.8), ('V', 'e', -0.8), ('V', 'o', -0.8), ('V', '.', -1.2), ('V', ',', -1.2),
+        ('W', 'A', -0.8), ('W', 'a', -0.6), ('W', 'e', -0.6), ('W', 'o', -0.6),
+        ('Y', 'A', -1.0), ('Y', 'a', -1.0), ('Y', 'e', -1.0), ('Y', 'o', -1.0), ('Y', '.', -1.2), ('Y', ',', -1.2),
+        ('r', '.', -1.0), ('r', ',', -1.0), ('v', '.', -0.8), ('v', ',', -0.8), ('y', '.', -0.8), ('y', ',', -0.8)
+    ];
+//
+
+#[derive(Clone, Debug)]
+pub struct StrokeGlyph {
+    /// Horizontal advance (glyph units)
+    pub advance : f32,
+    /// Strokes in glyph units, Y up
+    pub strokes : Vec<Polyline>
+}
+
+/// A single-stroke vector font, every stroke of a glyph is drawn exactly once
+#[derive(Clone, Debug)]
+pub struct StrokeFont {
+    pub glyphs : HashMap<char, StrokeGlyph>,
+    pub kerning : HashMap<(char, char), f32>
+}
+
+fn parse_strokes(data : &str) -> Vec<Polyline> {
+    data.split(';')
+        .filter(|stroke| !stroke.trim().is_empty())
+        .map(|stroke| {
+            let values : Vec<f32> = stroke.split_whitespace()
+                .map(|v| v.parse().expect("Invalid coordinate in the glyph table"))
+                .collect();
+
+            values.chunks_exact(2).map(|p| [ p[0], p[1] ]).collect()
+        })
+        .collect()
+}
+
+impl StrokeFont {
+    /// The built-in Hershey-style simplex font
+    pub fn simplex() -> Self {
+        Self {
+            glyphs: SIMPLEX_GLYPHS.iter()
+                .map(|&(c, advance, data)| (c, StrokeGlyph { advance, strokes: parse_strokes(data) }))
+                .collect(),
+            kerning: SIMPLEX_KERNING.iter()
+                .map(|&(left, right, adjustment)| ((left, right), adjustment))
+                .collect()
+        }
+    }
+
+    fn glyph(&self, c : char) -> Option<&StrokeGlyph> {
+        self.glyphs.get(&c).or_else(|| self.glyphs.get(&REPLACEMENT_CHAR))
+    }
+
+    /// Advances of all characters of the line including kerning (glyph units)
+    fn advances<'a>(&'a self, line : &'a str) -> impl Iterator<Item = f32> + 'a {
+        let mut prev : Option<char> = None;
+
+        line.chars().map(move |c| {
+            let kerning = prev.and_then(|p| self.kerning.get(&(p, c))).copied().unwrap_or(0.0);
+            prev = Some(c);
+
+            kerning + self.glyph(c).map(|glyph| glyph.advance).unwrap_or(0.0)
+        })
+    }
+}
+
+impl TextFont for StrokeFont {
+    fn line_width(&self, line : &str, size : f32) -> f32 {
+        self.advances(line).sum::<f32>() * size / UNITS_PER_EM
+    }
+
+    fn line_height(&self, size : f32) -> f32 {
+        LINE_HEIGHT * size / UNITS_PER_EM
+    }
+
+    fn render(&self, text : &str, style : &TextStyle) -> Drawing {
+        let scale = style.size / UNITS_PER_EM;
+        let line_height = self.line_height(style.size) * style.line_spacing;
+
+        let mut strokes = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let mut x = style.align.offset(self.line_width(line, style.size));
+            let baseline = ASCENDER * scale + index as f32 * line_height;
+            let mut prev : Option<char> = None;
+
+            for c in line.chars() {
+                let Some(glyph) = self.glyph(c) else {
+                    continue;
+                };
+
+                if !self.glyphs.contains_key(&c) {
+                    log::warn!("The font has no glyph for the character '{}', using '{}' instead", c, REPLACEMENT_CHAR);
+                }
+
+                x += prev.and_then(|p| self.kerning.get(&(p, c))).copied().unwrap_or(0.0) * scale;
+
+                for stroke in &glyph.strokes {
+                    strokes.push(stroke.iter().map(|p| [ x + p[0] * scale, baseline - p[1] * scale ]).collect());
+                }
+
+                x += glyph.advance * scale;
+                prev = Some(c);
+            }
+        }
+
+        Drawing {
+            strokes,
+            unit: DrawingUnit::Millimeter
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::drawing::text::TextAlign;
+
+    /// Style with a scale of one millimeter per glyph unit
+    fn unit_style() -> TextStyle {
+        TextStyle { size: UNITS_PER_EM, ..TextStyle::default() }
+    }
+
+    #[test]
+    fn glyph_table() {
+        let font = StrokeFont::simplex();
+
+        assert_eq!(font.glyphs.len(), SIMPLEX_GLYPHS.len());
+        assert!(font.glyphs[&' '].strokes.is_empty());
+
+        // Every stroke has at least two points
+        for (c, glyph) in &font.glyphs {
+            assert!(glyph.strokes.iter().all(|stroke| stroke.len() >= 2), "{}", c);
+        }
+
+        let h = &font.glyphs[&'H'];
+        assert_eq!(h.advance, 8.0);
+        assert_eq!(h.strokes, vec![
+            vec![ [ 1.0, 0.0 ], [ 1.0, 10.0 ] ],
+            vec![ [ 7.0, 0.0 ], [ 7.0, 10.0 ] ],
+            vec![ [ 1.0, 5.0 ], [ 7.0, 5.0 ] ]
+        ]);
+    }
+
+    #[test]
+    fn renders_glyph_strokes() {
+        let font = StrokeFont::simplex();
+
+        // Y is flipped, the baseline lies `ASCENDER` below the top of the line
+        assert_eq!(font.render("H", &unit_style()).strokes, vec![
+            vec![ [ 1.0, 11.0 ], [ 1.0, 1.0 ] ],
+            vec![ [ 7.0, 11.0 ], [ 7.0, 1.0 ] ],
+            vec![ [ 1.0, 6.0 ], [ 7.0, 6.0 ] ]
+        ]);
+
+        // Half the size halves the coordinates
+        let small = font.render("H", &TextStyle { size: UNITS_PER_EM / 2.0, ..TextStyle::default() });
+        assert_eq!(small.strokes[0], vec![ [ 0.5, 5.5 ], [ 0.5, 0.5 ] ]);
+
+        // Following lines start `LINE_HEIGHT` lower, following characters after the advance
+        let lines = font.render("H\n-H", &unit_style());
+        assert_eq!(lines.strokes[3], vec![ [ 1.0, 23.0 ], [ 5.0, 23.0 ] ]);
+        assert_eq!(lines.strokes[4], vec![ [ 7.0, 27.0 ], [ 7.0, 17.0 ] ]);
+    }
+
+    #[test]
+    fn kerning() {
+        let font = StrokeFont::simplex();
+        let style = unit_style();
+
+        assert_eq!(font.line_width("AV", style.size), 15.0);
+        assert_eq!(font.line_width("VA", style.size), 15.0);
+        assert_eq!(font.line_width("AH", style.size), 16.0);
+
+        // The V is moved closer to the A
+        let drawing = font.render("AV", &style);
+        assert_eq!(drawing.strokes[2][0], [ 7.5, 1.0 ]);
+    }
+
+    #[test]
+    fn line_widths() {
+        let font = StrokeFont::simplex();
+
+        assert_eq!(font.line_width("", 10.0), 0.0);
+        assert_eq!(font.line_width("H", UNITS_PER_EM), 8.0);
+        assert_eq!(font.line_width("H H", UNITS_PER_EM / 2.0), 10.5);
+        assert_eq!(font.line_height(UNITS_PER_EM / 2.0), LINE_HEIGHT / 2.0);
+
+        // Aligned lines are shifted by their width
+        let centered = font.render("H", &TextStyle { align: TextAlign::Center, ..unit_style() });
+        assert_eq!(centered.strokes[0], vec![ [ -3.0, 11.0 ], [ -3.0, 1.0 ] ]);
+    }
+
+    #[test]
+    fn fallback_glyph() {
+        let font = StrokeFont::simplex();
+        let style = unit_style();
+
+        assert!(!font.glyphs.contains_key(&'€'));
+        assert_eq!(font.line_width("€", style.size), font.line_width("?", style.size));
+        assert_eq!(font.render("€", &style).strokes, font.render("?", &style).strokes);
+    }
+}
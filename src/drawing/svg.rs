@@ -0,0 +1,705 @@
+use crate::drawing::{Drawing, DrawingUnit, Polyline};
+
+/// Maximum recursion depth when flattening bezier curves
+const FLATTEN_DEPTH : usize = 16;
+
+// Errors
+    #[derive(Debug, Clone)]
+    pub enum SvgError {
+        /// The document is no valid XML
+        Xml(String),
+        /// The path data of an element could not be parsed
+        BadPathData(String)
+    }
+
+    impl core::fmt::Display for SvgError {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            match self {
+                Self::Xml(msg) => f.write_fmt(format_args!("Xml: The SVG document could not be parsed! ({msg})")),
+                Self::BadPathData(data) => f.write_fmt(format_args!("BadPathData: The path data '{data}' is invalid!"))
+            }
+        }
+    }
+
+    impl std::error::Error for SvgError { }
+//
+
+// Transforms
+    /// An affine transformation `[a, b, c, d, e, f]` as used by the SVG `matrix()` transform
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Matrix(pub [f32; 6]);
+
+    impl Matrix {
+        pub const IDENTITY : Self = Self([ 1.0, 0.0, 0.0, 1.0, 0.0, 0.0 ]);
+
+        pub fn translate(x : f32, y : f32) -> Self {
+            Self([ 1.0, 0.0, 0.0, 1.0, x, y ])
+        }
+
+        pub fn scale(x : f32, y : f32) -> Self {
+            Self([ x, 0.0, 0.0, y, 0.0, 0.0 ])
+        }
+
+        /// Rotation by the given angle in degrees
+        pub fn rotate(angle : f32) -> Self {
+            let (sin, cos) = angle.to_radians().sin_cos();
+            Self([ cos, sin, -sin, cos, 0.0, 0.0 ])
+        }
+
+        /// Returns the transform applying `other` first and then `self`
+        pub fn then(&self, other : &Matrix) -> Self {
+            let [ a1, b1, c1, d1, e1, f1 ] = self.0;
+            let [ a2, b2, c2, d2, e2, f2 ] = other.0;
+
+            Self([
+                a1 * a2 + c1 * b2,
+                b1 * a2 + d1 * b2,
+                a1 * c2 + c1 * d2,
+                b1 * c2 + d1 * d2,
+                a1 * e2 + c1 * f2 + e1,
+                b1 * e2 + d1 * f2 + f1
+            ])
+        }
+
+        pub fn apply(&self, p : [f32; 2]) -> [f32; 2] {
+            let [ a, b, c, d, e, f ] = self.0;
+            [ a * p[0] + c * p[1] + e, b * p[0] + d * p[1] + f ]
+        }
+    }
+
+    /// Parses a SVG `transform` attribute, e.g. `translate(10, 20) rotate(45)`
+    pub fn parse_transform(value : &str) -> Matrix {
+        let mut matrix = Matrix::IDENTITY;
+
+        for part in value.split(')') {
+            let Some((name, args)) = part.split_once('(') else {
+                continue;
+            };
+
+            let args = parse_numbers(args);
+            let arg = |index : usize, default : f32| args.get(index).copied().unwrap_or(default);
+
+            let local = match name.trim().trim_start_matches(',').trim() {
+                "matrix" if args.len() == 6 => Matrix([ args[0], args[1], args[2], args[3], args[4], args[5] ]),
+                "translate" => Matrix::translate(arg(0, 0.0), arg(1, 0.0)),
+                "scale" => Matrix::scale(arg(0, 1.0), arg(1, arg(0, 1.0))),
+                "rotate" => {
+                    let (cx, cy) = (arg(1, 0.0), arg(2, 0.0));
+                    Matrix::translate(cx, cy).then(&Matrix::rotate(arg(0, 0.0))).then(&Matrix::translate(-cx, -cy))
+                },
+                "skewX" => Matrix([ 1.0, 0.0, arg(0, 0.0).to_radians().tan(), 1.0, 0.0, 0.0 ]),
+                "skewY" => Matrix([ 1.0, arg(0, 0.0).to_radians().tan(), 0.0, 1.0, 0.0, 0.0 ]),
+                _ => {
+                    log::warn!("Ignoring unsupported SVG transform '{}'", part);
+                    Matrix::IDENTITY
+                }
+            };
+
+            matrix = matrix.then(&local);
+        }
+
+        matrix
+    }
+//
+
+// Parsing helpers
+    /// Parses a list of numbers separated by whitespace and/or commas
+    pub fn parse_numbers(value : &str) -> Vec<f32> {
+        let mut lexer = Lexer::new(value);
+        let mut numbers = Vec::new();
+
+        while let Some(number) = lexer.number() {
+            numbers.push(number);
+        }
+
+        numbers
+    }
+
+    /// Converts a length with an optional unit into millimeters, plain numbers are treated as pixels (96 per inch)
+    pub fn parse_length_mm(value : &str) -> Option<f32> {
+        let value = value.trim();
+        let split = value.find(|c : char| c.is_ascii_alphabetic() || c == '%').unwrap_or(value.len());
+        let number : f32 = value[.. split].trim().parse().ok()?;
+
+        let factor = match &value[split ..] {
+            "mm" => 1.0,
+            "cm" => 10.0,
+            "in" => 25.4,
+            "pt" => 25.4 / 72.0,
+            "pc" => 25.4 / 6.0,
+            "" | "px" => 25.4 / 96.0,
+            _ => return None
+        };
+
+        Some(number * factor)
+    }
+
+    /// Splits SVG path data and number lists into commands and numbers
+    struct Lexer<'a> {
+        data : &'a [u8],
+        pos : usize
+    }
+
+    impl<'a> Lexer<'a> {
+        fn new(data : &'a str) -> Self {
+            Self { data: data.as_bytes(), pos: 0 }
+        }
+
+        fn skip_separators(&mut self) {
+            while (self.pos < self.data.len()) && (self.data[self.pos].is_ascii_whitespace() || self.data[self.pos] == b',') {
+                self.pos += 1;
+            }
+        }
+
+        fn is_done(&mut self) -> bool {
+            self.skip_separators();
+            self.pos >= self.data.len()
+        }
+
+        /// Returns the next command letter, if the next token is one
+        fn command(&mut self) -> Option<u8> {
+            self.skip_separators();
+
+            let c = *self.data.get(self.pos)?;
+            if c.is_ascii_alphabetic() && (c != b'e') && (c != b'E') {
+                self.pos += 1;
+                Some(c)
+            } else {
+                None
+            }
+        }
+
+        fn number(&mut self) -> Option<f32> {
+            self.skip_separators();
+            let start = self.pos;
+
+            if matches!(self.data.get(self.pos), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+
+            let mut seen_dot = false;
+            let mut seen_digit = false;
+
+            while let Some(&c) = self.data.get(self.pos) {
+                if c.is_ascii_digit() {
+                    seen_digit = true;
+                } else if (c == b'.') && !seen_dot {
+                    seen_dot = true;
+                } else {
+                    break;
+                }
+
+                self.pos += 1;
+            }
+
+            // Exponent
+            if seen_digit && matches!(self.data.get(self.pos), Some(b'e' | b'E')) {
+                let mut exp_pos = self.pos + 1;
+
+                if matches!(self.data.get(exp_pos), Some(b'+' | b'-')) {
+                    exp_pos += 1;
+                }
+
+                if self.data.get(exp_pos).is_some_and(|c| c.is_ascii_digit()) {
+                    self.pos = exp_pos;
+
+                    while self.data.get(self.pos).is_some_and(|c| c.is_ascii_digit()) {
+                        self.pos += 1;
+                    }
+                }
+            }
+
+            if !seen_digit {
+                self.pos = start;
+                return None;
+            }
+
+            core::str::from_utf8(&self.data[start .. self.pos]).ok()?.parse().ok()
+        }
+
+        /// Arc flags may be written without separators, e.g. `a1 1 0 011 1`
+        fn flag(&mut self) -> Option<bool> {
+            self.skip_separators();
+
+            let flag = match self.data.get(self.pos)? {
+                b'0' => false,
+                b'1' => true,
+                _ => return None
+            };
+
+            self.pos += 1;
+            Some(flag)
+        }
+    }
+//
+
+// Curves
+    /// Collects flattened strokes in millimeters
+    pub(crate) struct StrokeBuilder {
+        pub(crate) strokes : Vec<Polyline>,
+        pub(crate) current : Polyline,
+        pub(crate) tolerance : f32
+    }
+
+    impl StrokeBuilder {
+        pub(crate) fn move_to(&mut self, p : [f32; 2]) {
+            self.finish();
+            self.current.push(p);
+        }
+
+        pub(crate) fn line_to(&mut self, p : [f32; 2]) {
+            if self.current.last() != Some(&p) {
+                self.current.push(p);
+            }
+        }
+
+        pub(crate) fn finish(&mut self) {
+            let stroke = core::mem::take(&mut self.current);
+
+            if stroke.len() > 1 {
+                self.strokes.push(stroke);
+            }
+        }
+
+        pub(crate) fn cubic_to(&mut self, p1 : [f32; 2], p2 : [f32; 2], p3 : [f32; 2]) {
+            let p0 = *self.current.last().unwrap_or(&p1);
+            self.flatten_cubic([ p0, p1, p2, p3 ], 0);
+        }
+
+        fn flatten_cubic(&mut self, c : [[f32; 2]; 4], depth : usize) {
+            let flatness = crate::drawing::simplify::dist_to_segment(c[1], c[0], c[3])
+                .max(crate::drawing::simplify::dist_to_segment(c[2], c[0], c[3]));
+
+            if (flatness <= self.tolerance) || (depth >= FLATTEN_DEPTH) {
+                self.line_to(c[3]);
+                return;
+            }
+
+            // De Casteljau split at t = 0.5
+            let mid = |a : [f32; 2], b : [f32; 2]| [ (a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0 ];
+            let p01 = mid(c[0], c[1]);
+            let p12 = mid(c[1], c[2]);
+            let p23 = mid(c[2], c[3]);
+            let p012 = mid(p01, p12);
+            let p123 = mid(p12, p23);
+            let p0123 = mid(p012, p123);
+
+            self.flatten_cubic([ c[0], p01, p012, p0123 ], depth + 1);
+            self.flatten_cubic([ p0123, p123, p23, c[3] ], depth + 1);
+        }
+    }
+
+    /// Converts an elliptical arc (SVG endpoint parameterization) into cubic bezier segments `[c1, c2, end]` in local coordinates
+    pub fn arc_to_cubics(from : [f32; 2], radii : [f32; 2], x_rotation : f32, large_arc : bool, sweep : bool, to : [f32; 2]) -> Vec<[[f32; 2]; 3]> {
+        let (mut rx, mut ry) = (radii[0].abs(), radii[1].abs());
+
+        if (rx == 0.0) || (ry == 0.0) || (from == to) {
+            return vec![ [ from, to, to ] ];
+        }
+
+        let (sin_phi, cos_phi) = x_rotation.to_radians().sin_cos();
+
+        // Step 1: Compute (x1', y1')
+        let dx = (from[0] - to[0]) / 2.0;
+        let dy = (from[1] - to[1]) / 2.0;
+        let x1 = cos_phi * dx + sin_phi * dy;
+        let y1 = -sin_phi * dx + cos_phi * dy;
+
+        // Scale up radii that are too small
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+
+        // Step 2: Compute the center (cx', cy')
+        let num = (rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1).max(0.0);
+        let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+        let mut coef = (num / den).sqrt();
+        if large_arc == sweep {
+            coef = -coef;
+        }
+
+        let cx1 = coef * rx * y1 / ry;
+        let cy1 = -coef * ry * x1 / rx;
+
+        // Step 3: Center in the original coordinates
+        let cx = cos_phi * cx1 - sin_phi * cy1 + (from[0] + to[0]) / 2.0;
+        let cy = sin_phi * cx1 + cos_phi * cy1 + (from[1] + to[1]) / 2.0;
+
+        // Step 4: Angles
+        let angle = |ux : f32, uy : f32, vx : f32, vy : f32| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+
+        let theta1 = angle(1.0, 0.0, (x1 - cx1) / rx, (y1 - cy1) / ry);
+        let mut delta = angle((x1 - cx1) / rx, (y1 - cy1) / ry, (-x1 - cx1) / rx, (-y1 - cy1) / ry);
+
+        if !sweep && (delta > 0.0) {
+            delta -= 2.0 * core::f32::consts::PI;
+        } else if sweep && (delta < 0.0) {
+            delta += 2.0 * core::f32::consts::PI;
+        }
+
+        // Split into segments of at most 90 degrees
+        let segments = (delta.abs() / core::f32::consts::FRAC_PI_2).ceil().max(1.0) as usize;
+        let seg_delta = delta / segments as f32;
+        let kappa = 4.0 / 3.0 * (seg_delta / 4.0).tan();
+
+        let point = |t : f32| {
+            let (sin_t, cos_t) = t.sin_cos();
+            [ cx + rx * cos_t * cos_phi - ry * sin_t * sin_phi, cy + rx * cos_t * sin_phi + ry * sin_t * cos_phi ]
+        };
+
+        let derivative = |t : f32| {
+            let (sin_t, cos_t) = t.sin_cos();
+            [ -rx * sin_t * cos_phi - ry * cos_t * sin_phi, -rx * sin_t * sin_phi + ry * cos_t * cos_phi ]
+        };
+
+        (0 .. segments).map(|i| {
+            let t1 = theta1 + seg_delta * i as f32;
+            let t2 = t1 + seg_delta;
+
+            let (p1, d1) = (point(t1), derivative(t1));
+            let (p2, d2) = (point(t2), derivative(t2));
+
+            let end = if i == segments - 1 { to } else { p2 };
+
+            [
+                [ p1[0] + kappa * d1[0], p1[1] + kappa * d1[1] ],
+                [ p2[0] - kappa * d2[0], p2[1] - kappa * d2[1] ],
+                end
+            ]
+        }).collect()
+    }
+//
+
+// Path data
+    /// Parses SVG path data and adds the flattened strokes to the builder, transforming all points with the given matrix
+    fn add_path_data(builder : &mut StrokeBuilder, data : &str, matrix : &Matrix) -> Result<(), SvgError> {
+        let bad_data = || SvgError::BadPathData(data.to_string());
+
+        let mut lexer = Lexer::new(data);
+
+        // Current point, subpath start and last control point (all in local coordinates)
+        let mut pos = [ 0.0, 0.0 ];
+        let mut start = [ 0.0, 0.0 ];
+        let mut last_ctrl : Option<(u8, [f32; 2])> = None;
+        let mut command = b'M';
+
+        let cubic = |builder : &mut StrokeBuilder, c1 : [f32; 2], c2 : [f32; 2], end : [f32; 2]| {
+            builder.cubic_to(matrix.apply(c1), matrix.apply(c2), matrix.apply(end))
+        };
+
+        while !lexer.is_done() {
+            if let Some(c) = lexer.command() {
+                command = c;
+            } else if matches!(command, b'Z' | b'z') {
+                return Err(bad_data());
+            }
+
+            let relative = command.is_ascii_lowercase();
+            let base = if relative { pos } else { [ 0.0, 0.0 ] };
+            let mut num = || lexer.number().ok_or_else(bad_data);
+
+            match command.to_ascii_uppercase() {
+                b'M' => {
+                    let x = num()?;
+                    let p = [ base[0] + x, base[1] + num()? ];
+                    builder.move_to(matrix.apply(p));
+                    pos = p;
+                    start = p;
+
+                    // Subsequent pairs are implicit line commands
+                    command = if relative { b'l' } else { b'L' };
+                    last_ctrl = None;
+                },
+                b'L' => {
+                    let x = num()?;
+                    pos = [ base[0] + x, base[1] + num()? ];
+                    builder.line_to(matrix.apply(pos));
+                    last_ctrl = None;
+                },
+                b'H' => {
+                    pos = [ base[0] + num()?, pos[1] ];
+                    builder.line_to(matrix.apply(pos));
+                    last_ctrl = None;
+                },
+                b'V' => {
+                    pos = [ pos[0], base[1] + num()? ];
+                    builder.line_to(matrix.apply(pos));
+                    last_ctrl = None;
+                },
+                b'C' | b'S' => {
+                    let c1 = if command.eq_ignore_ascii_case(&b'C') {
+                        let x = num()?;
+                        [ base[0] + x, base[1] + num()? ]
+                    } else {
+                        // Reflection of the last cubic control point
+                        match last_ctrl {
+                            Some((b'C', ctrl)) => [ 2.0 * pos[0] - ctrl[0], 2.0 * pos[1] - ctrl[1] ],
+                            _ => pos
+                        }
+                    };
+
+                    let x = num()?;
+                    let c2 = [ base[0] + x, base[1] + num()? ];
+                    let x = num()?;
+                    let end = [ base[0] + x, base[1] + num()? ];
+
+                    cubic(builder, c1, c2, end);
+                    last_ctrl = Some((b'C', c2));
+                    pos = end;
+                },
+                b'Q' | b'T' => {
+                    let ctrl = if command.eq_ignore_ascii_case(&b'Q') {
+                        let x = num()?;
+                        [ base[0] + x, base[1] + num()? ]
+                    } else {
+                        // Reflection of the last quadratic control point
+                        match last_ctrl {
+                            Some((b'Q', ctrl)) => [ 2.0 * pos[0] - ctrl[0], 2.0 * pos[1] - ctrl[1] ],
+                            _ => pos
+                        }
+                    };
+
+                    let x = num()?;
+                    let end = [ base[0] + x, base[1] + num()? ];
+
+                    // Elevate the quadratic curve to a cubic one
+                    let c1 = [ pos[0] + 2.0 / 3.0 * (ctrl[0] - pos[0]), pos[1] + 2.0 / 3.0 * (ctrl[1] - pos[1]) ];
+                    let c2 = [ end[0] + 2.0 / 3.0 * (ctrl[0] - end[0]), end[1] + 2.0 / 3.0 * (ctrl[1] - end[1]) ];
+
+                    cubic(builder, c1, c2, end);
+                    last_ctrl = Some((b'Q', ctrl));
+                    pos = end;
+                },
+                b'A' => {
+                    let rx = num()?;
+                    let ry = num()?;
+                    let x_rotation = num()?;
+                    let large_arc = lexer.flag().ok_or_else(bad_data)?;
+                    let sweep = lexer.flag().ok_or_else(bad_data)?;
+                    let x = lexer.number().ok_or_else(bad_data)?;
+                    let end = [ base[0] + x, base[1] + lexer.number().ok_or_else(bad_data)? ];
+
+                    for [ c1, c2, p ] in arc_to_cubics(pos, [ rx, ry ], x_rotation, large_arc, sweep, end) {
+                        cubic(builder, c1, c2, p);
+                    }
+
+                    last_ctrl = None;
+                    pos = end;
+                },
+                b'Z' => {
+                    builder.line_to(matrix.apply(start));
+                    pos = start;
+                    last_ctrl = None;
+                },
+                _ => return Err(bad_data())
+            }
+        }
+
+        builder.finish();
+        Ok(())
+    }
+//
+
+// Elements
+    fn attr_num(node : &roxmltree::Node, name : &str) -> f32 {
+        node.attribute(name).and_then(|v| parse_numbers(v).first().copied()).unwrap_or(0.0)
+    }
+
+    fn add_node(builder : &mut StrokeBuilder, node : roxmltree::Node, parent : &Matrix) -> Result<(), SvgError> {
+        if !node.is_element() || (node.attribute("display") == Some("none")) {
+            return Ok(());
+        }
+
+        let matrix = match node.attribute("transform") {
+            Some(value) => parent.then(&parse_transform(value)),
+            None => *parent
+        };
+
+        match node.tag_name().name() {
+            "svg" | "g" | "a" | "switch" => {
+                for child in node.children() {
+                    add_node(builder, child, &matrix)?;
+                }
+            },
+            "path" => {
+                add_path_data(builder, node.attribute("d").unwrap_or(""), &matrix)?;
+            },
+            "line" => {
+                builder.move_to(matrix.apply([ attr_num(&node, "x1"), attr_num(&node, "y1") ]));
+                builder.line_to(matrix.apply([ attr_num(&node, "x2"), attr_num(&node, "y2") ]));
+                builder.finish();
+            },
+            tag @ ("polyline" | "polygon") => {
+                let numbers = parse_numbers(node.attribute("points").unwrap_or(""));
+                let points : Vec<[f32; 2]> = numbers.chunks_exact(2).map(|p| matrix.apply([ p[0], p[1] ])).collect();
+
+                if let Some(&first) = points.first() {
+                    builder.move_to(first);
+                    points[1 ..].iter().for_each(|&p| builder.line_to(p));
+
+                    if tag == "polygon" {
+                        builder.line_to(first);
+                    }
+
+                    builder.finish();
+                }
+            },
+            "rect" => {
+                let (x, y) = (attr_num(&node, "x"), attr_num(&node, "y"));
+                let (w, h) = (attr_num(&node, "width"), attr_num(&node, "height"));
+
+                if (w <= 0.0) || (h <= 0.0) {
+                    return Ok(());
+                }
+
+                let rx_attr = node.attribute("rx").map(|_| attr_num(&node, "rx"));
+                let ry_attr = node.attribute("ry").map(|_| attr_num(&node, "ry"));
+                let rx = rx_attr.or(ry_attr).unwrap_or(0.0).min(w / 2.0);
+                let ry = ry_attr.or(rx_attr).unwrap_or(0.0).min(h / 2.0);
+
+                let data = if (rx > 0.0) && (ry > 0.0) {
+                    format!(
+                        "M{} {} H{} A{rx} {ry} 0 0 1 {} {} V{} A{rx} {ry} 0 0 1 {} {} H{} A{rx} {ry} 0 0 1 {} {} V{} A{rx} {ry} 0 0 1 {} {} Z",
+                        x + rx, y, x + w - rx, x + w, y + ry, y + h - ry, x + w - rx, y + h, x + rx, x, y + h - ry, y + ry, x + rx, y
+                    )
+                } else {
+                    format!("M{x} {y} H{} V{} H{x} Z", x + w, y + h)
+                };
+
+                add_path_data(builder, &data, &matrix)?;
+            },
+            tag @ ("circle" | "ellipse") => {
+                let (cx, cy) = (attr_num(&node, "cx"), attr_num(&node, "cy"));
+                let (rx, ry) = if tag == "circle" {
+                    (attr_num(&node, "r"), attr_num(&node, "r"))
+                } else {
+                    (attr_num(&node, "rx"), attr_num(&node, "ry"))
+                };
+
+                let data = format!(
+                    "M{} {cy} A{rx} {ry} 0 0 1 {cx} {} A{rx} {ry} 0 0 1 {} {cy} A{rx} {ry} 0 0 1 {cx} {} A{rx} {ry} 0 0 1 {} {cy} Z",
+                    cx + rx, cy + ry, cx - rx, cy - ry, cx + rx
+                );
+
+                add_path_data(builder, &data, &matrix)?;
+            },
+            // Definitions, styles, texts etc. are not drawn
+            _ => { }
+        }
+
+        Ok(())
+    }
+//
+
+/// Parses an SVG document into a drawing in millimeters, flattening all curves within the given tolerance (mm)
+pub fn parse_svg(text : &str, tolerance : f32) -> Result<Drawing, SvgError> {
+    let doc = roxmltree::Document::parse(text).map_err(|err| SvgError::Xml(err.to_string()))?;
+    let root = doc.root_element();
+
+    // Map the viewBox onto the physical size of the document
+    let view_box = root.attribute("viewBox").map(parse_numbers).filter(|v| v.len() == 4);
+    let width = root.attribute("width").and_then(parse_length_mm);
+    let height = root.attribute("height").and_then(parse_length_mm);
+
+    let px = parse_length_mm("1px").unwrap_or(1.0);
+
+    let base = match view_box {
+        Some(vb) => {
+            let scale_x = width.map(|w| w / vb[2]).unwrap_or(px);
+            let scale_y = height.map(|h| h / vb[3]).unwrap_or(scale_x);
+
+            Matrix::scale(scale_x, scale_y).then(&Matrix::translate(-vb[0], -vb[1]))
+        },
+        None => Matrix::scale(px, px)
+    };
+
+    let mut builder = StrokeBuilder {
+        strokes: Vec::new(),
+        current: Vec::new(),
+        tolerance
+    };
+
+    add_node(&mut builder, root, &base)?;
+    builder.finish();
+
+    Ok(Drawing {
+        strokes: builder.strokes,
+        unit: DrawingUnit::Millimeter
+    })
+}
+
+pub fn load_svg(path : &str, tolerance : f32) -> Result<Drawing, syact::Error> {
+    Ok(parse_svg(&std::fs::read_to_string(path)?, tolerance)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wraps the given elements into a document with one user unit per millimeter
+    fn parse(body : &str) -> Drawing {
+        let text = format!(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100mm" height="100mm" viewBox="0 0 100 100">{body}</svg>"#);
+        parse_svg(&text, 0.01).unwrap()
+    }
+
+    fn assert_points(points : &[[f32; 2]], expected : &[[f32; 2]]) {
+        assert_eq!(points.len(), expected.len(), "{:?} != {:?}", points, expected);
+
+        for (p, e) in points.iter().zip(expected) {
+            assert!((p[0] - e[0]).abs() < 1e-3 && (p[1] - e[1]).abs() < 1e-3, "{:?} != {:?}", points, expected);
+        }
+    }
+
+    #[test]
+    fn absolute_and_relative_lines() {
+        let drawing = parse(r#"<path d="M10 10 h10 v10 H10 z"/>"#);
+
+        assert_eq!(drawing.strokes.len(), 1);
+        assert_points(&drawing.strokes[0], &[ [ 10.0, 10.0 ], [ 20.0, 10.0 ], [ 20.0, 20.0 ], [ 10.0, 20.0 ], [ 10.0, 10.0 ] ]);
+    }
+
+    #[test]
+    fn moves_start_new_strokes() {
+        let drawing = parse(r#"<path d="M0 0 L5 0 m0 5 l5 0 5 5"/>"#);
+
+        assert_eq!(drawing.strokes.len(), 2);
+        assert_points(&drawing.strokes[0], &[ [ 0.0, 0.0 ], [ 5.0, 0.0 ] ]);
+        // Further coordinate pairs after a lineto continue the line
+        assert_points(&drawing.strokes[1], &[ [ 5.0, 5.0 ], [ 10.0, 5.0 ], [ 15.0, 10.0 ] ]);
+    }
+
+    #[test]
+    fn curves_end_at_their_endpoint() {
+        let drawing = parse(r#"<path d="M0 0 C0 10 10 10 10 0 S20 -10 20 0"/>"#);
+        let stroke = &drawing.strokes[0];
+
+        assert!(stroke.len() > 3);
+        assert_points(&[ stroke[0], stroke[stroke.len() - 1] ], &[ [ 0.0, 0.0 ], [ 20.0, 0.0 ] ]);
+    }
+
+    #[test]
+    fn nested_transforms() {
+        let drawing = parse(r#"<g transform="translate(10 20)"><path d="M0 0 L10 0" transform="scale(2)"/></g>"#);
+
+        assert_points(&drawing.strokes[0], &[ [ 10.0, 20.0 ], [ 30.0, 20.0 ] ]);
+    }
+
+    #[test]
+    fn transform_lists() {
+        assert_points(&[ parse_transform("rotate(90)").apply([ 1.0, 0.0 ]) ], &[ [ 0.0, 1.0 ] ]);
+        assert_points(&[ parse_transform("rotate(90, 5, 5)").apply([ 5.0, 0.0 ]) ], &[ [ 10.0, 5.0 ] ]);
+        // The rightmost transform is applied first
+        assert_points(&[ parse_transform("translate(10, 0) scale(2)").apply([ 1.0, 1.0 ]) ], &[ [ 12.0, 2.0 ] ]);
+        assert_points(&[ parse_transform("matrix(1 0 0 1 3 4)").apply([ 1.0, 1.0 ]) ], &[ [ 4.0, 5.0 ] ]);
+    }
+
+    #[test]
+    fn view_box_scales_to_mm() {
+        let text = r#"<svg xmlns="http://www.w3.org/2000/svg" width="50mm" height="50mm" viewBox="10 10 100 100"><path d="M10 10 L110 110"/></svg>"#;
+        let drawing = parse_svg(text, 0.01).unwrap();
+
+        assert_points(&drawing.strokes[0], &[ [ 0.0, 0.0 ], [ 50.0, 50.0 ] ]);
+    }
+}
@@ -0,0 +1,434 @@
+use core::str::FromStr;
+
+use ttf_parser::{Face, OutlineBuilder};
+
+use crate::drawing::{Drawing, DrawingUnit, Polyline};
+use crate::drawing::svg::StrokeBuilder;
+
+/// Font shipped with the repository, relative to the control directory
+pub const DEFAULT_FONT : &str = "assets/Consolas.ttf";
+
+// Errors
+    #[derive(Debug, Clone)]
+    pub enum TextError {
+        /// The font file could not be parsed
+        BadFont(String)
+    }
+
+    impl core::fmt::Display for TextError {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            match self {
+                Self::BadFont(msg) => f.write_fmt(format_args!("BadFont: {msg}!"))
+            }
+        }
+    }
+
+    impl std::error::Error for TextError { }
+//
+
+/// Horizontal alignment of every line relative to the text origin
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right
+}
+
+impl FromStr for TextAlign {
+    type Err = String;
+
+    fn from_str(s : &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "left" => Ok(Self::Left),
+            "center" => Ok(Self::Center),
+            "right" => Ok(Self::Right),
+            _ => Err(format!("Invalid text alignment '{}'! (left, center or right)", s))
+        }
+    }
+}
+
+impl TextAlign {
+    /// Horizontal offset of a line with the given width
+    pub fn offset(&self, width : f32) -> f32 {
+        match self {
+            Self::Left => 0.0,
+            Self::Center => -width / 2.0,
+            Self::Right => -width
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TextStyle {
+    /// Font size, the height of the em square (mm)
+    pub size : f32,
+    /// Distance between baselines relative to the line height of the font
+    pub line_spacing : f32,
+    pub align : TextAlign,
+    /// Spacing of the hatch lines filling outline glyphs (mm), outlines only if `None`
+    pub hatch : Option<f32>,
+    /// Maximum deviation when flattening glyph curves (mm)
+    pub tolerance : f32
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            size: 10.0,
+            line_spacing: 1.0,
+            align: TextAlign::Left,
+            hatch: None,
+            tolerance: 0.05
+        }
+    }
+}
+
+/// A font turning text into strokes
+pub trait TextFont {
+    /// Width of a single line of text in the given size (mm)
+    fn line_width(&self, line : &str, size : f32) -> f32;
+
+    /// Distance between two baselines in the given size (mm), without the line spacing of the style
+    fn line_height(&self, size : f32) -> f32;
+
+    /// Turns the text into strokes (mm), one line per `\n`
+    ///
+    /// The text origin is the left (or aligned) end of the first line, at the top of the line. Lines continue in +Y direction.
+    fn render(&self, text : &str, style : &TextStyle) -> Drawing;
+}
+
+/// Breaks the text into lines no wider than the given width (mm), only splitting at spaces
+///
+/// Words that are wider than the box on their own are kept on a separate line.
+pub fn wrap_text(font : &dyn TextFont, text : &str, size : f32, width : f32) -> String {
+    let mut lines : Vec<String> = Vec::new();
+
+    for paragraph in text.lines() {
+        let mut line = String::new();
+
+        for word in paragraph.split(' ').filter(|word| !word.is_empty()) {
+            let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+
+            if line.is_empty() || (font.line_width(&candidate, size) <= width) {
+                line = candidate;
+            } else {
+                lines.push(core::mem::replace(&mut line, word.to_string()));
+            }
+        }
+
+        lines.push(line);
+    }
+
+    lines.join("\n")
+}
+
+/// Wraps the text into a box of the given size (mm) and renders it, the text origin being the top left corner of the box
+///
+/// Lines that do not fit into the height of the box are dropped.
+pub fn render_in_box(font : &dyn TextFont, text : &str, style : &TextStyle, box_size : [f32; 2]) -> Drawing {
+    let wrapped = wrap_text(font, text, style.size, box_size[0]);
+
+    let line_height = font.line_height(style.size);
+    let line_pitch = line_height * style.line_spacing;
+    let max_lines = (((box_size[1] - line_height) / line_pitch).floor() + 1.0).max(0.0) as usize;
+
+    let line_count = wrapped.lines().count();
+
+    if line_count > max_lines {
+        log::warn!("The text needs {} lines, but only {} fit into the box, dropping the rest", line_count, max_lines);
+    }
+
+    let fitting : Vec<&str> = wrapped.lines().take(max_lines).collect();
+    let mut drawing = font.render(&fitting.join("\n"), style);
+
+    // Align the lines inside the box
+    let shift = -style.align.offset(box_size[0]);
+
+    for point in drawing.strokes.iter_mut().flatten() {
+        point[0] += shift;
+    }
+
+    drawing
+}
+
+/// Fills closed contours with horizontal hatch lines (even-odd rule), alternating their direction row by row
+pub fn hatch(contours : &[Polyline], spacing : f32) -> Vec<Polyline> {
+    let mut lines = Vec::new();
+
+    let (min_y, max_y) = contours.iter().flatten()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), p| (min.min(p[1]), max.max(p[1])));
+
+    if spacing.is_nan() || (spacing <= 0.0) || (min_y > max_y) {
+        return lines;
+    }
+
+    let mut y = min_y + spacing / 2.0;
+    let mut reverse = false;
+
+    while y < max_y {
+        let mut crossings : Vec<f32> = contours.iter()
+            .flat_map(|contour| contour.windows(2))
+            .filter(|w| (w[0][1] <= y) != (w[1][1] <= y))
+            .map(|w| w[0][0] + (y - w[0][1]) / (w[1][1] - w[0][1]) * (w[1][0] - w[0][0]))
+            .collect();
+
+        crossings.sort_by(|a, b| a.total_cmp(b));
+
+        let mut row : Vec<Polyline> = crossings.chunks_exact(2)
+            .map(|pair| vec![ [ pair[0], y ], [ pair[1], y ] ])
+            .collect();
+
+        if reverse {
+            row.reverse();
+
+            for line in &mut row {
+                line.reverse();
+            }
+        }
+
+        lines.extend(row);
+        reverse = !reverse;
+        y += spacing;
+    }
+
+    lines
+}
+
+/// Maps glyph outlines (font units, Y up) onto the paper (mm, Y down)
+struct GlyphOutline {
+    builder : StrokeBuilder,
+    scale : f32,
+    origin : [f32; 2],
+    start : [f32; 2]
+}
+
+impl GlyphOutline {
+    fn point(&self, x : f32, y : f32) -> [f32; 2] {
+        [ self.origin[0] + x * self.scale, self.origin[1] - y * self.scale ]
+    }
+}
+
+impl OutlineBuilder for GlyphOutline {
+    fn move_to(&mut self, x : f32, y : f32) {
+        self.start = self.point(x, y);
+        self.builder.move_to(self.start);
+    }
+
+    fn line_to(&mut self, x : f32, y : f32) {
+        let p = self.point(x, y);
+        self.builder.line_to(p);
+    }
+
+    fn quad_to(&mut self, x1 : f32, y1 : f32, x : f32, y : f32) {
+        // Elevate the quadratic curve to a cubic one
+        let p0 = *self.builder.current.last().unwrap_or(&self.start);
+        let p1 = self.point(x1, y1);
+        let p2 = self.point(x, y);
+
+        self.builder.cubic_to(
+            [ p0[0] + 2.0 / 3.0 * (p1[0] - p0[0]), p0[1] + 2.0 / 3.0 * (p1[1] - p0[1]) ],
+            [ p2[0] + 2.0 / 3.0 * (p1[0] - p2[0]), p2[1] + 2.0 / 3.0 * (p1[1] - p2[1]) ],
+            p2
+        );
+    }
+
+    fn curve_to(&mut self, x1 : f32, y1 : f32, x2 : f32, y2 : f32, x : f32, y : f32) {
+        let (c1, c2, p) = (self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+        self.builder.cubic_to(c1, c2, p);
+    }
+
+    fn close(&mut self) {
+        self.builder.line_to(self.start);
+        self.builder.finish();
+    }
+}
+
+/// A TrueType / OpenType font used to turn text into strokes
+pub struct Font {
+    data : Vec<u8>
+}
+
+impl Font {
+    pub fn from_data(data : Vec<u8>) -> Result<Self, TextError> {
+        Face::parse(&data, 0).map_err(|err| TextError::BadFont(err.to_string()))?;
+        Ok(Self { data })
+    }
+
+    pub fn load(path : &str) -> Result<Self, syact::Error> {
+        Ok(Self::from_data(std::fs::read(path)?)?)
+    }
+
+    fn face(&self) -> Face<'_> {
+        Face::parse(&self.data, 0).expect("The font has been checked when loading")
+    }
+}
+
+impl TextFont for Font {
+    fn line_width(&self, line : &str, size : f32) -> f32 {
+        let face = self.face();
+        let scale = size / face.units_per_em() as f32;
+
+        line.chars()
+            .filter_map(|c| face.glyph_index(c))
+            .filter_map(|glyph| face.glyph_hor_advance(glyph))
+            .map(|advance| advance as f32 * scale)
+            .sum()
+    }
+
+    fn line_height(&self, size : f32) -> f32 {
+        let face = self.face();
+        (face.ascender() as f32 - face.descender() as f32 + face.line_gap() as f32) * size / face.units_per_em() as f32
+    }
+
+    /// Turns the text into glyph outlines (mm), optionally filled with hatch lines
+    fn render(&self, text : &str, style : &TextStyle) -> Drawing {
+        let face = self.face();
+        let scale = style.size / face.units_per_em() as f32;
+        let line_height = self.line_height(style.size) * style.line_spacing;
+
+        let mut strokes = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let mut x = style.align.offset(self.line_width(line, style.size));
+            let baseline = face.ascender() as f32 * scale + index as f32 * line_height;
+
+            for c in line.chars() {
+                let Some(glyph) = face.glyph_index(c) else {
+                    log::warn!("The font has no glyph for the character '{}', skipping it", c);
+                    continue;
+                };
+
+                let mut outline = GlyphOutline {
+                    builder: StrokeBuilder {
+                        strokes: Vec::new(),
+                        current: Vec::new(),
+                        tolerance: style.tolerance
+                    },
+                    scale,
+                    origin: [ x, baseline ],
+                    start: [ x, baseline ]
+                };
+
+                face.outline_glyph(glyph, &mut outline);
+                outline.builder.finish();
+
+                if let Some(spacing) = style.hatch {
+                    let fill = hatch(&outline.builder.strokes, spacing);
+                    strokes.extend(outline.builder.strokes);
+                    strokes.extend(fill);
+                } else {
+                    strokes.extend(outline.builder.strokes);
+                }
+
+                x += face.glyph_hor_advance(glyph).unwrap_or(0) as f32 * scale;
+            }
+        }
+
+        Drawing {
+            strokes,
+            unit: DrawingUnit::Millimeter
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Monospaced font with square characters, rendering every line as a single stroke along its top
+    struct BlockFont;
+
+    impl TextFont for BlockFont {
+        fn line_width(&self, line : &str, size : f32) -> f32 {
+            line.chars().count() as f32 * size
+        }
+
+        fn line_height(&self, size : f32) -> f32 {
+            size
+        }
+
+        fn render(&self, text : &str, style : &TextStyle) -> Drawing {
+            let pitch = self.line_height(style.size) * style.line_spacing;
+
+            Drawing {
+                strokes: text.lines().enumerate().map(|(index, line)| {
+                    let x = style.align.offset(self.line_width(line, style.size));
+                    let y = index as f32 * pitch;
+                    vec![ [ x, y ], [ x + self.line_width(line, style.size), y ] ]
+                }).collect(),
+                unit: DrawingUnit::Millimeter
+            }
+        }
+    }
+
+    fn square(min : f32, max : f32) -> Polyline {
+        vec![ [ min, min ], [ max, min ], [ max, max ], [ min, max ], [ min, min ] ]
+    }
+
+    #[test]
+    fn wraps_at_spaces() {
+        assert_eq!(wrap_text(&BlockFont, "aa bb cc", 1.0, 5.0), "aa bb\ncc");
+        assert_eq!(wrap_text(&BlockFont, "aa bb cc", 2.0, 5.0), "aa\nbb\ncc");
+
+        // Paragraphs are kept, words wider than the box get their own line
+        assert_eq!(wrap_text(&BlockFont, "a\nabcdefg b", 1.0, 5.0), "a\nabcdefg\nb");
+        assert_eq!(wrap_text(&BlockFont, "a   b", 1.0, 5.0), "a b");
+    }
+
+    #[test]
+    fn renders_into_box() {
+        let style = TextStyle { size: 1.0, ..TextStyle::default() };
+
+        // Three lines fit into a box 3mm high, the fourth one is dropped
+        let drawing = render_in_box(&BlockFont, "aa bb cc dd", &style, [ 2.0, 3.0 ]);
+        assert_eq!(drawing.strokes, vec![
+            vec![ [ 0.0, 0.0 ], [ 2.0, 0.0 ] ],
+            vec![ [ 0.0, 1.0 ], [ 2.0, 1.0 ] ],
+            vec![ [ 0.0, 2.0 ], [ 2.0, 2.0 ] ]
+        ]);
+
+        // Aligned lines stay inside the box
+        let style = TextStyle { size: 1.0, align: TextAlign::Right, ..TextStyle::default() };
+        let drawing = render_in_box(&BlockFont, "a bb", &style, [ 5.0, 10.0 ]);
+        assert_eq!(drawing.strokes, vec![ vec![ [ 1.0, 0.0 ], [ 5.0, 0.0 ] ] ]);
+
+        let style = TextStyle { size: 1.0, align: TextAlign::Center, ..TextStyle::default() };
+        let drawing = render_in_box(&BlockFont, "aa", &style, [ 4.0, 10.0 ]);
+        assert_eq!(drawing.strokes, vec![ vec![ [ 1.0, 0.0 ], [ 3.0, 0.0 ] ] ]);
+
+        // A box lower than a single line takes no text
+        assert!(render_in_box(&BlockFont, "aa", &style, [ 4.0, 0.5 ]).strokes.is_empty());
+    }
+
+    #[test]
+    fn hatches_squares() {
+        let lines = hatch(&[ square(0.0, 10.0) ], 2.0);
+
+        // Rows alternate their direction
+        assert_eq!(lines, vec![
+            vec![ [ 0.0, 1.0 ], [ 10.0, 1.0 ] ],
+            vec![ [ 10.0, 3.0 ], [ 0.0, 3.0 ] ],
+            vec![ [ 0.0, 5.0 ], [ 10.0, 5.0 ] ],
+            vec![ [ 10.0, 7.0 ], [ 0.0, 7.0 ] ],
+            vec![ [ 0.0, 9.0 ], [ 10.0, 9.0 ] ]
+        ]);
+    }
+
+    #[test]
+    fn hatch_skips_holes() {
+        let lines = hatch(&[ square(0.0, 10.0), square(4.0, 6.0) ], 2.0);
+
+        // The row through the hole is split in two
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[2], vec![ [ 0.0, 5.0 ], [ 4.0, 5.0 ] ]);
+        assert_eq!(lines[3], vec![ [ 6.0, 5.0 ], [ 10.0, 5.0 ] ]);
+    }
+
+    #[test]
+    fn hatch_rejects_invalid_spacing() {
+        assert!(hatch(&[ square(0.0, 10.0) ], 0.0).is_empty());
+        assert!(hatch(&[ square(0.0, 10.0) ], f32::NAN).is_empty());
+        assert!(hatch(&[], 1.0).is_empty());
+    }
+}
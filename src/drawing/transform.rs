@@ -0,0 +1,220 @@
+use serde::{Serialize, Deserialize};
+use syunit::*;
+
+use crate::config::DrakeConfig;
+use crate::drawing::{Drawing, DrawingUnit, Line};
+
+/// Maps drawing units (e.g. pixels) onto the paper, in millimeters relative to the drawing origin
+///
+/// Points are scaled first, then mirrored, rotated around the drawing origin and finally offset.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DrawingTransform {
+    /// Drawing units per millimeter
+    pub units_per_mm : f32,
+    /// Counter-clockwise rotation (rad)
+    pub rotation : f32,
+    /// Mirrors the drawing along the Y-axis (flips X)
+    pub mirror_x : bool,
+    /// Mirrors the drawing along the X-axis (flips Y)
+    pub mirror_y : bool,
+    /// Offset relative to the drawing origin (mm)
+    pub offset : [f32; 2]
+}
+
+impl Default for DrawingTransform {
+    fn default() -> Self {
+        Self::scale(1.0)
+    }
+}
+
+impl DrawingTransform {
+    /// A transform that only scales, with the given amount of drawing units per millimeter
+    pub fn scale(units_per_mm : f32) -> Self {
+        Self {
+            units_per_mm,
+            rotation: 0.0,
+            mirror_x: false,
+            mirror_y: false,
+            offset: [ 0.0, 0.0 ]
+        }
+    }
+
+    /// Creates the transform described by the config for drawings with the given unit
+    pub fn from_config(config : &DrakeConfig, unit : DrawingUnit) -> Self {
+        Self {
+            units_per_mm: match unit {
+                DrawingUnit::Pixel => config.pixel_per_mm,
+                DrawingUnit::Millimeter => 1.0
+            },
+            rotation: config.drawing_rotation,
+            mirror_x: config.drawing_mirror[0],
+            mirror_y: config.drawing_mirror[1],
+            offset: config.drawing_offset
+        }
+    }
+
+    /// Transforms a point in drawing units into millimeters relative to the drawing origin
+    pub fn apply(&self, point : [f32; 2]) -> [f32; 2] {
+        let mut x = point[0] / self.units_per_mm;
+        let mut y = point[1] / self.units_per_mm;
+
+        if self.mirror_x {
+            x = -x;
+        }
+
+        if self.mirror_y {
+            y = -y;
+        }
+
+        let (sin, cos) = self.rotation.sin_cos();
+
+        [
+            x * cos - y * sin + self.offset[0],
+            x * sin + y * cos + self.offset[1]
+        ]
+    }
+
+    pub fn convert_point(&self, point : [f32; 2]) -> [Phi; 2] {
+        let [ x, y ] = self.apply(point);
+        [ Phi(x), Phi(y) ]
+    }
+
+    pub fn convert_line(&self, line : Line) -> [[Phi; 2]; 2] {
+        [ self.convert_point(line.p1), self.convert_point(line.p2) ]
+    }
+
+    /// Minimum and maximum corner of the transformed drawing (mm), `None` if the drawing is empty
+    pub fn bounds(&self, drawing : &Drawing) -> Option<([f32; 2], [f32; 2])> {
+        let mut points = drawing.strokes.iter().flatten().map(|&p| self.apply(p));
+        let first = points.next()?;
+
+        Some(points.fold((first, first), |(min, max), p| {
+            ([ min[0].min(p[0]), min[1].min(p[1]) ], [ max[0].max(p[0]), max[1].max(p[1]) ])
+        }))
+    }
+
+    /// Scales and offsets the transform, so the drawing fills the paper (keeping its aspect ratio) and sits centered on it
+    ///
+    /// The paper spans from the drawing origin to `paper_size` (mm), leaving the given margin on every side.
+    pub fn fit_to_paper(&mut self, drawing : &Drawing, paper_size : [f32; 2], margin : f32) {
+        self.offset = [ 0.0, 0.0 ];
+
+        let Some((min, max)) = self.bounds(drawing) else {
+            return;
+        };
+
+        let size = [ max[0] - min[0], max[1] - min[1] ];
+        let available = [ paper_size[0] - 2.0 * margin, paper_size[1] - 2.0 * margin ];
+
+        let factor = (available[0] / size[0]).min(available[1] / size[1]);
+
+        if factor.is_finite() && (factor > 0.0) {
+            self.units_per_mm /= factor;
+        }
+
+        // Center the scaled drawing
+        if let Some((min, max)) = self.bounds(drawing) {
+            self.offset = [
+                paper_size[0] / 2.0 - (min[0] + max[0]) / 2.0,
+                paper_size[1] / 2.0 - (min[1] + max[1]) / 2.0
+            ];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::f32::consts::FRAC_PI_2;
+
+    fn assert_close(point : [f32; 2], expected : [f32; 2]) {
+        assert!((point[0] - expected[0]).abs() < 1e-4 && (point[1] - expected[1]).abs() < 1e-4, "{:?} != {:?}", point, expected);
+    }
+
+    fn drawing(strokes : Vec<Vec<[f32; 2]>>) -> Drawing {
+        Drawing { strokes, unit: DrawingUnit::Pixel }
+    }
+
+    #[test]
+    fn scales_pixels() {
+        let transform = DrawingTransform::scale(4.0);
+
+        assert_close(transform.apply([ 0.0, 0.0 ]), [ 0.0, 0.0 ]);
+        assert_close(transform.apply([ 8.0, -2.0 ]), [ 2.0, -0.5 ]);
+        assert_close(DrawingTransform::default().apply([ 8.0, -2.0 ]), [ 8.0, -2.0 ]);
+    }
+
+    #[test]
+    fn rotates_mirrors_and_offsets() {
+        let mut transform = DrawingTransform::scale(2.0);
+        transform.rotation = FRAC_PI_2;
+        assert_close(transform.apply([ 4.0, 2.0 ]), [ -1.0, 2.0 ]);
+
+        let mut transform = DrawingTransform { mirror_x: true, ..Default::default() };
+        assert_close(transform.apply([ 3.0, 1.0 ]), [ -3.0, 1.0 ]);
+
+        transform.mirror_x = false;
+        transform.mirror_y = true;
+        assert_close(transform.apply([ 3.0, 1.0 ]), [ 3.0, -1.0 ]);
+
+        // Mirrored before being rotated, offset last
+        transform.rotation = FRAC_PI_2;
+        transform.offset = [ 10.0, 20.0 ];
+        assert_close(transform.apply([ 3.0, 1.0 ]), [ 11.0, 23.0 ]);
+
+        assert_eq!(transform.convert_point([ 0.0, 0.0 ]), [ Phi(10.0), Phi(20.0) ]);
+    }
+
+    #[test]
+    fn bounds() {
+        let mut transform = DrawingTransform::scale(2.0);
+        transform.offset = [ 1.0, 1.0 ];
+
+        let (min, max) = transform.bounds(&drawing(vec![ vec![ [ 0.0, 4.0 ], [ 6.0, -2.0 ] ], vec![ [ -2.0, 0.0 ] ] ])).unwrap();
+        assert_close(min, [ 0.0, 0.0 ]);
+        assert_close(max, [ 4.0, 3.0 ]);
+
+        assert!(transform.bounds(&drawing(vec![ ])).is_none());
+    }
+
+    #[test]
+    fn fits_to_paper() {
+        // 200 x 100 pixels onto a 100 x 100 mm paper with 10 mm margins
+        let lines = drawing(vec![ vec![ [ 0.0, 0.0 ], [ 200.0, 100.0 ] ] ]);
+        let mut transform = DrawingTransform::scale(1.0);
+        transform.offset = [ 50.0, 50.0 ];
+
+        transform.fit_to_paper(&lines, [ 100.0, 100.0 ], 10.0);
+
+        assert!((transform.units_per_mm - 2.5).abs() < 1e-4, "{}", transform.units_per_mm);
+
+        // The width fills the paper, the height is centered
+        let (min, max) = transform.bounds(&lines).unwrap();
+        assert_close(min, [ 10.0, 30.0 ]);
+        assert_close(max, [ 90.0, 70.0 ]);
+
+        // Rotated drawings are fitted with their rotated size
+        transform.rotation = FRAC_PI_2;
+        transform.fit_to_paper(&lines, [ 100.0, 100.0 ], 10.0);
+
+        let (min, max) = transform.bounds(&lines).unwrap();
+        assert_close(min, [ 30.0, 10.0 ]);
+        assert_close(max, [ 70.0, 90.0 ]);
+    }
+
+    #[test]
+    fn fits_single_points() {
+        let point = drawing(vec![ vec![ [ 4.0, 4.0 ] ] ]);
+        let mut transform = DrawingTransform::scale(2.0);
+
+        // Points cannot be scaled, only centered
+        transform.fit_to_paper(&point, [ 100.0, 60.0 ], 5.0);
+        assert_eq!(transform.units_per_mm, 2.0);
+        assert_close(transform.apply([ 4.0, 4.0 ]), [ 50.0, 30.0 ]);
+
+        // Empty drawings reset the offset
+        transform.fit_to_paper(&drawing(vec![ ]), [ 100.0, 60.0 ], 5.0);
+        assert_eq!(transform.offset, [ 0.0, 0.0 ]);
+    }
+}
@@ -0,0 +1,454 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use embedded_hal::digital::{self, ErrorType, InputPin, OutputPin, StatefulOutputPin};
+use embedded_hal::i2c::{self, I2c, NoAcknowledgeSource, Operation};
+
+/// Default I2C address of the PCA9685 board
+pub const PCA9685_ADDRESS : u8 = 0x40;
+
+// Virtual hardware
+    /// Callback invoked with the new level whenever a virtual pin changes
+    pub type PinListener = Box<dyn Fn(bool) + Send + Sync>;
+
+    /// Shared state of a single virtual GPIO pin
+    #[derive(Default)]
+    pub struct VirtualPinState {
+        level : AtomicBool,
+        listeners : Mutex<Vec<PinListener>>
+    }
+
+    impl VirtualPinState {
+        pub fn level(&self) -> bool {
+            self.level.load(Ordering::Relaxed)
+        }
+
+        pub fn set_level(&self, level : bool) {
+            if self.level.swap(level, Ordering::Relaxed) != level {
+                for listener in self.listeners.lock().unwrap().iter() {
+                    listener(level);
+                }
+            }
+        }
+
+        /// Registers a listener that gets called every time the level of the pin changes
+        pub fn on_change(&self, listener : PinListener) {
+            self.listeners.lock().unwrap().push(listener);
+        }
+    }
+
+    impl core::fmt::Debug for VirtualPinState {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            f.debug_struct("VirtualPinState").field("level", &self.level()).finish()
+        }
+    }
+
+    /// An in-memory output pin, writing into the state shared with its `VirtualBoard`
+    #[derive(Debug, Clone)]
+    pub struct VirtualOutputPin {
+        state : Arc<VirtualPinState>
+    }
+
+    impl ErrorType for VirtualOutputPin {
+        type Error = core::convert::Infallible;
+    }
+
+    impl OutputPin for VirtualOutputPin {
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.state.set_level(false);
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.state.set_level(true);
+            Ok(())
+        }
+    }
+
+    impl StatefulOutputPin for VirtualOutputPin {
+        fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+            Ok(self.state.level())
+        }
+
+        fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+            Ok(!self.state.level())
+        }
+    }
+
+    /// An in-memory input pin, reading the state shared with its `VirtualBoard`
+    #[derive(Debug, Clone)]
+    pub struct VirtualInputPin {
+        state : Arc<VirtualPinState>
+    }
+
+    impl ErrorType for VirtualInputPin {
+        type Error = core::convert::Infallible;
+    }
+
+    impl InputPin for VirtualInputPin {
+        fn is_high(&mut self) -> Result<bool, Self::Error> {
+            Ok(self.state.level())
+        }
+
+        fn is_low(&mut self) -> Result<bool, Self::Error> {
+            Ok(!self.state.level())
+        }
+    }
+
+    /// Register map of an emulated PCA9685 PWM board
+    #[derive(Debug)]
+    pub struct VirtualPca9685 {
+        /// Whether the board answers on the bus
+        pub connected : bool,
+        pub registers : [u8; 256],
+        pointer : u8
+    }
+
+    impl Default for VirtualPca9685 {
+        fn default() -> Self {
+            let mut registers = [0; 256];
+            registers[0x00] = 0x11;     // MODE1: Sleep + ALLCALL
+            registers[0x01] = 0x04;     // MODE2: OUTDRV
+            registers[0xFE] = 0x1E;     // PRE_SCALE: 200 Hz
+
+            Self {
+                connected: true,
+                registers,
+                pointer: 0
+            }
+        }
+    }
+
+    impl VirtualPca9685 {
+        /// Returns the 12-bit OFF tick count written to the given channel
+        pub fn channel_off(&self, channel : u8) -> u16 {
+            let base = 0x06 + 4 * channel as usize;
+            u16::from_le_bytes([ self.registers[base + 2], self.registers[base + 3] ]) & 0x0FFF
+        }
+
+        fn write(&mut self, bytes : &[u8]) {
+            if let Some((&reg, data)) = bytes.split_first() {
+                self.pointer = reg;
+
+                for &byte in data {
+                    self.registers[self.pointer as usize] = byte;
+                    self.pointer = self.pointer.wrapping_add(1);
+                }
+            }
+        }
+
+        fn read(&mut self, buffer : &mut [u8]) {
+            for byte in buffer {
+                *byte = self.registers[self.pointer as usize];
+                self.pointer = self.pointer.wrapping_add(1);
+            }
+        }
+    }
+
+    /// An in-memory I2C bus with an emulated PCA9685 attached
+    #[derive(Debug, Clone)]
+    pub struct VirtualI2c {
+        board : Arc<Mutex<VirtualPca9685>>
+    }
+
+    impl i2c::ErrorType for VirtualI2c {
+        type Error = i2c::ErrorKind;
+    }
+
+    impl I2c for VirtualI2c {
+        fn transaction(&mut self, address : u8, operations : &mut [Operation<'_>]) -> Result<(), Self::Error> {
+            let mut board = self.board.lock().unwrap();
+
+            if (address != PCA9685_ADDRESS) || !board.connected {
+                return Err(i2c::ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+            }
+
+            for op in operations {
+                match op {
+                    Operation::Write(bytes) => board.write(bytes),
+                    Operation::Read(buffer) => board.read(buffer)
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    /// A collection of in-memory pins and an I2C bus, replacing the Raspberry Pi for development and tests
+    #[derive(Debug, Clone, Default)]
+    pub struct VirtualBoard {
+        pins : Arc<Mutex<HashMap<u8, Arc<VirtualPinState>>>>,
+        pca9685 : Arc<Mutex<VirtualPca9685>>
+    }
+
+    impl VirtualBoard {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Returns the shared state of the given pin, creating it if required
+        pub fn pin(&self, pin : u8) -> Arc<VirtualPinState> {
+            self.pins.lock().unwrap().entry(pin).or_default().clone()
+        }
+
+        pub fn output(&self, pin : u8) -> VirtualOutputPin {
+            VirtualOutputPin { state: self.pin(pin) }
+        }
+
+        pub fn input(&self, pin : u8) -> VirtualInputPin {
+            VirtualInputPin { state: self.pin(pin) }
+        }
+
+        pub fn level(&self, pin : u8) -> bool {
+            self.pin(pin).level()
+        }
+
+        /// Sets the level of a pin, e.g. to simulate a button press or a triggered switch
+        pub fn set_level(&self, pin : u8, level : bool) {
+            self.pin(pin).set_level(level)
+        }
+
+        pub fn i2c(&self) -> VirtualI2c {
+            VirtualI2c { board: self.pca9685.clone() }
+        }
+
+        pub fn pca9685(&self) -> Arc<Mutex<VirtualPca9685>> {
+            self.pca9685.clone()
+        }
+    }
+//
+
+// Pins
+    /// Output pin of either the Raspberry Pi or a `VirtualBoard`, clones drive the same pin
+    #[derive(Clone)]
+    pub enum HalOutputPin {
+        #[cfg(feature = "rasp")]
+        Rasp(Arc<Mutex<rppal::gpio::OutputPin>>),
+        Virtual(VirtualOutputPin)
+    }
+
+    impl HalOutputPin {
+        pub fn is_set_high(&self) -> bool {
+            match self {
+                #[cfg(feature = "rasp")]
+                Self::Rasp(pin) => pin.lock().unwrap().is_set_high(),
+                Self::Virtual(pin) => pin.state.level()
+            }
+        }
+
+        pub fn write(&mut self, value : bool) {
+            match self {
+                #[cfg(feature = "rasp")]
+                Self::Rasp(pin) => pin.lock().unwrap().write(rppal::gpio::Level::from(value)),
+                Self::Virtual(pin) => pin.state.set_level(value)
+            }
+        }
+    }
+
+    impl ErrorType for HalOutputPin {
+        type Error = digital::ErrorKind;
+    }
+
+    impl OutputPin for HalOutputPin {
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.write(false);
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.write(true);
+            Ok(())
+        }
+    }
+
+    impl StatefulOutputPin for HalOutputPin {
+        fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+            Ok(HalOutputPin::is_set_high(self))
+        }
+
+        fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+            Ok(!HalOutputPin::is_set_high(self))
+        }
+    }
+
+    /// Input pin of either the Raspberry Pi or a `VirtualBoard`, clones read the same pin
+    #[derive(Clone)]
+    pub enum HalInputPin {
+        #[cfg(feature = "rasp")]
+        Rasp(Arc<Mutex<rppal::gpio::InputPin>>),
+        Virtual(VirtualInputPin)
+    }
+
+    impl HalInputPin {
+        pub fn is_high(&self) -> bool {
+            match self {
+                #[cfg(feature = "rasp")]
+                Self::Rasp(pin) => pin.lock().unwrap().is_high(),
+                Self::Virtual(pin) => pin.state.level()
+            }
+        }
+    }
+
+    impl ErrorType for HalInputPin {
+        type Error = digital::ErrorKind;
+    }
+
+    impl InputPin for HalInputPin {
+        fn is_high(&mut self) -> Result<bool, Self::Error> {
+            Ok(HalInputPin::is_high(self))
+        }
+
+        fn is_low(&mut self) -> Result<bool, Self::Error> {
+            Ok(!HalInputPin::is_high(self))
+        }
+    }
+//
+
+// I2C
+    /// I2C bus of either the Raspberry Pi or a `VirtualBoard`
+    pub enum HalI2c {
+        #[cfg(feature = "rasp")]
+        Rasp(rppal::i2c::I2c),
+        Virtual(VirtualI2c)
+    }
+
+    impl i2c::ErrorType for HalI2c {
+        type Error = i2c::ErrorKind;
+    }
+
+    impl I2c for HalI2c {
+        fn transaction(&mut self, address : u8, operations : &mut [Operation<'_>]) -> Result<(), Self::Error> {
+            match self {
+                #[cfg(feature = "rasp")]
+                Self::Rasp(bus) => bus.transaction(address, operations).map_err(|err| i2c::Error::kind(&err)),
+                Self::Virtual(bus) => bus.transaction(address, operations)
+            }
+        }
+    }
+
+    /// An I2C bus shared by multiple drivers, clones use the same bus
+    #[derive(Clone)]
+    pub struct SharedI2c {
+        bus : Arc<Mutex<HalI2c>>
+    }
+
+    impl SharedI2c {
+        pub fn new(bus : HalI2c) -> Self {
+            Self { bus: Arc::new(Mutex::new(bus)) }
+        }
+    }
+
+    impl i2c::ErrorType for SharedI2c {
+        type Error = i2c::ErrorKind;
+    }
+
+    impl I2c for SharedI2c {
+        fn transaction(&mut self, address : u8, operations : &mut [Operation<'_>]) -> Result<(), Self::Error> {
+            self.bus.lock().unwrap().transaction(address, operations)
+        }
+    }
+//
+
+/// The GPIO peripheral of the Raspberry Pi, keeping every pin it has handed out so further requests share it
+#[cfg(feature = "rasp")]
+pub struct RaspGpio {
+    gpio : rppal::gpio::Gpio,
+    outputs : Mutex<HashMap<u8, Arc<Mutex<rppal::gpio::OutputPin>>>>,
+    inputs : Mutex<HashMap<u8, Arc<Mutex<rppal::gpio::InputPin>>>>
+}
+
+#[cfg(feature = "rasp")]
+impl RaspGpio {
+    pub fn new() -> Result<Self, syact::Error> {
+        Ok(Self {
+            gpio: rppal::gpio::Gpio::new()?,
+            outputs: Mutex::new(HashMap::new()),
+            inputs: Mutex::new(HashMap::new())
+        })
+    }
+
+    pub fn output(&self, pin : u8) -> Result<Arc<Mutex<rppal::gpio::OutputPin>>, syact::Error> {
+        let mut outputs = self.outputs.lock().unwrap();
+
+        if let Some(output) = outputs.get(&pin) {
+            return Ok(output.clone());
+        }
+
+        let output = Arc::new(Mutex::new(self.gpio.get(pin)?.into_output()));
+        outputs.insert(pin, output.clone());
+        Ok(output)
+    }
+
+    pub fn input(&self, pin : u8) -> Result<Arc<Mutex<rppal::gpio::InputPin>>, syact::Error> {
+        let mut inputs = self.inputs.lock().unwrap();
+
+        if let Some(input) = inputs.get(&pin) {
+            return Ok(input.clone());
+        }
+
+        let input = Arc::new(Mutex::new(self.gpio.get(pin)?.into_input()));
+        inputs.insert(pin, input.clone());
+        Ok(input)
+    }
+}
+
+/// The hardware the station runs on, handing out pins and buses by their BCM numbers
+///
+/// Requesting a pin again returns a handle to the same pin, e.g. for the step generator driving the pins of the steppers.
+pub enum Hal {
+    #[cfg(feature = "rasp")]
+    Rasp(RaspGpio),
+    Virtual(VirtualBoard)
+}
+
+impl Hal {
+    /// Opens the GPIO peripheral of the Raspberry Pi
+    #[cfg(feature = "rasp")]
+    pub fn rasp() -> Result<Self, syact::Error> {
+        Ok(Self::Rasp(RaspGpio::new()?))
+    }
+
+    /// Opens the Raspberry Pi with the `rasp` feature enabled, otherwise falls back to a fresh `VirtualBoard`
+    pub fn platform() -> Result<Self, syact::Error> {
+        #[cfg(feature = "rasp")]
+        return Self::rasp();
+
+        #[cfg(not(feature = "rasp"))]
+        return Ok(Self::Virtual(VirtualBoard::new()));
+    }
+
+    pub fn is_virtual(&self) -> bool {
+        matches!(self, Self::Virtual(_))
+    }
+
+    pub fn output(&self, pin : u8) -> Result<HalOutputPin, syact::Error> {
+        Ok(match self {
+            #[cfg(feature = "rasp")]
+            Self::Rasp(gpio) => HalOutputPin::Rasp(gpio.output(pin)?),
+            Self::Virtual(board) => HalOutputPin::Virtual(board.output(pin))
+        })
+    }
+
+    pub fn output_low(&self, pin : u8) -> Result<HalOutputPin, syact::Error> {
+        let mut output = self.output(pin)?;
+        output.write(false);
+        Ok(output)
+    }
+
+    pub fn input(&self, pin : u8) -> Result<HalInputPin, syact::Error> {
+        Ok(match self {
+            #[cfg(feature = "rasp")]
+            Self::Rasp(gpio) => HalInputPin::Rasp(gpio.input(pin)?),
+            Self::Virtual(board) => HalInputPin::Virtual(board.input(pin))
+        })
+    }
+
+    pub fn i2c(&self) -> Result<HalI2c, syact::Error> {
+        Ok(match self {
+            #[cfg(feature = "rasp")]
+            Self::Rasp(_) => HalI2c::Rasp(rppal::i2c::I2c::new()?),
+            Self::Virtual(board) => HalI2c::Virtual(board.i2c())
+        })
+    }
+}
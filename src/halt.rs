@@ -0,0 +1,94 @@
+use core::fmt::Display;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use syact::prelude::*;
+
+use crate::pen_lift::PenState;
+
+// Errors
+    /// State the robot has been left in after a halt
+    #[derive(Clone, Debug)]
+    pub struct HaltReport {
+        /// Absolute positions of all axes (mm)
+        pub gammas : [Gamma; 3],
+        pub pen : PenState,
+        /// Whether the servo table could be opened
+        pub table_open : bool
+    }
+
+    impl Display for HaltReport {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_fmt(format_args!("position: [ {}, {}, {} ], pen: {:?}, table: {}",
+                self.gammas[0], self.gammas[1], self.gammas[2], self.pen, if self.table_open { "open" } else { "unknown" }
+            ))
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    pub enum HaltError {
+        /// The halt button has been pressed, the current move has been aborted
+        Halted(HaltReport)
+    }
+
+    impl Display for HaltError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::Halted(report) => f.write_fmt(format_args!("Halted: The job has been stopped by the halt button! ({})", report))
+            }
+        }
+    }
+
+    impl std::error::Error for HaltError { }
+//
+
+/// Halt request shared between the halt monitor, the axes of the robot and the station
+#[derive(Clone, Default)]
+pub struct HaltSignal {
+    requested : Arc<AtomicBool>
+}
+
+impl HaltSignal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether a halt has been requested and clears the request, so the axes can move again
+    pub fn take(&self) -> bool {
+        self.requested.swap(false, Ordering::Relaxed)
+    }
+
+    /// Interruptor stopping an axis in both directions while a halt is requested
+    pub fn guard(&self) -> HaltGuard {
+        HaltGuard { signal: self.clone() }
+    }
+}
+
+/// Stops the axis it is attached to as soon as a halt is requested
+pub struct HaltGuard {
+    signal : HaltSignal
+}
+
+impl Interruptor for HaltGuard {
+    fn dir(&self) -> Option<Direction> {
+        None
+    }
+
+    fn set_dir(&mut self, _dir : Direction) { }
+
+    fn check(&mut self, _gamma : Gamma) -> Option<InterruptReason> {
+        if self.signal.is_requested() {
+            Some(InterruptReason::Error)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::{Serialize, Deserialize};
+
+use crate::config::DrakeConfig;
+
+/// Pen speeds used by a job (mm/s)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct JobSpeeds {
+    /// Speed of the pen while drawing
+    pub draw : f32,
+    /// Speed of the pen while traveling between strokes
+    pub travel : f32,
+    /// Speed of the Z-axis when lifting or lowering the pen
+    pub lift : f32
+}
+
+impl JobSpeeds {
+    pub fn from_config(config : &DrakeConfig) -> Self {
+        Self {
+            draw: config.drawing_speed,
+            travel: config.travel_speed,
+            lift: config.lift_speed
+        }
+    }
+
+    /// Replaces all speeds given by the job settings
+    pub fn apply(&mut self, settings : &JobSettings) {
+        self.draw = settings.draw_speed.unwrap_or(self.draw);
+        self.travel = settings.travel_speed.unwrap_or(self.travel);
+        self.lift = settings.lift_speed.unwrap_or(self.lift);
+    }
+}
+
+/// Settings of a single job overriding the config, loaded from a job file or the command line
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct JobSettings {
+    #[serde(default)]
+    pub draw_speed : Option<f32>,
+    #[serde(default)]
+    pub travel_speed : Option<f32>,
+    #[serde(default)]
+    pub lift_speed : Option<f32>,
+    /// Initial speed override (%)
+    #[serde(default)]
+    pub speed_override : Option<u32>
+}
+
+impl JobSettings {
+    pub fn parse_from_file(path : &str) -> Result<Self, syact::Error> {
+        Ok(serde_json::from_str::<Self>(
+            std::fs::read_to_string(path)?.as_str()
+        )?)
+    }
+
+    /// Combines both settings, the values of `other` taking precedence
+    pub fn merge(&self, other : &JobSettings) -> Self {
+        Self {
+            draw_speed: other.draw_speed.or(self.draw_speed),
+            travel_speed: other.travel_speed.or(self.travel_speed),
+            lift_speed: other.lift_speed.or(self.lift_speed),
+            speed_override: other.speed_override.or(self.speed_override)
+        }
+    }
+}
+
+/// Pause state of the running job, shared between the job runner and everything that may pause it (start button, console)
+#[derive(Clone, Debug, Default)]
+pub struct PauseSignal {
+    paused : Arc<AtomicBool>
+}
+
+impl PauseSignal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Pauses the job at the end of the next segment
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::Relaxed);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::Relaxed);
+    }
+
+    /// Pauses a running and resumes a paused job, returns whether the job is paused now
+    pub fn toggle(&self) -> bool {
+        !self.paused.fetch_xor(true, Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,259 @@
+use core::fmt::Display;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Serialize, Deserialize};
+
+use crate::drawing::transform::DrawingTransform;
+use crate::job::JobSettings;
+
+/// Name of the journal file inside the log directory
+pub const JOURNAL_FILE : &str = "job_journal.json";
+/// Maximum amount of lines drawn between two checkpoints, limiting both the writes and the lines drawn twice when resuming
+pub const CHECKPOINT_LINES : usize = 10;
+
+// FNV-1a
+    const FNV_OFFSET_BASIS : u64 = 0xcbf29ce484222325;
+    const FNV_PRIME : u64 = 0x100000001b3;
+
+    /// 64 bit FNV-1a hash of the given data
+    pub fn fnv1a(data : &[u8]) -> u64 {
+        data.iter().fold(FNV_OFFSET_BASIS, |hash, &byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME))
+    }
+
+    /// FNV-1a hash of a file, formatted as hex string
+    pub fn hash_file(path : &str) -> Result<String, syact::Error> {
+        Ok(format!("{:016x}", fnv1a(&std::fs::read(path)?)))
+    }
+//
+
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0)
+}
+
+// Errors
+    #[derive(Clone, Debug)]
+    pub enum JournalError {
+        /// There is no unfinished job to resume
+        NothingToResume,
+        /// The source file has changed since the job has been started (expected hash, actual hash)
+        SourceChanged(String, String),
+        /// The drawing loaded for resuming has a different amount of lines (expected, actual)
+        LineCountMismatch(usize, usize)
+    }
+
+    impl Display for JournalError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::NothingToResume => f.write_str("NothingToResume: The journal has no unfinished job!"),
+                Self::SourceChanged(expected, actual) =>
+                    f.write_fmt(format_args!("SourceChanged: The source file has changed since the job has been started (hash {} instead of {})!", actual, expected)),
+                Self::LineCountMismatch(expected, actual) =>
+                    f.write_fmt(format_args!("LineCountMismatch: The drawing has {} lines instead of {}!", actual, expected))
+            }
+        }
+    }
+
+    impl std::error::Error for JournalError { }
+//
+
+/// Processing applied to the source file when loading the drawing, repeated when resuming
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DrawingOptions {
+    /// Maximum deviation when flattening curves (mm)
+    pub tolerance : f32,
+    pub optimise : bool,
+    /// Tolerance of the simplification (mm), not simplified if `None`
+    pub simplify : Option<f32>
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct JobStats {
+    /// Lines of the whole drawing
+    pub lines_total : usize,
+    /// Lines drawn so far, every line before this index is complete
+    pub lines_done : usize,
+    /// Length of all lines drawn so far (mm)
+    pub draw_distance : f32,
+    /// Start of the job (unix time, s)
+    pub started_at : u64,
+    /// Last checkpoint (unix time, s)
+    pub updated_at : u64,
+    /// Amount of times the job has been resumed
+    pub resumes : u32
+}
+
+/// Progress of a job, written after every stroke so the job can be resumed if the controller dies
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobJournal {
+    /// Path of the source file
+    pub source : String,
+    /// FNV-1a hash of the source file
+    pub source_hash : String,
+    pub options : DrawingOptions,
+    pub transform : DrawingTransform,
+    pub settings : JobSettings,
+    pub stats : JobStats,
+    pub finished : bool
+}
+
+impl JobJournal {
+    pub fn new(source : &str, options : DrawingOptions, transform : DrawingTransform, settings : JobSettings, lines_total : usize)
+    -> Result<Self, syact::Error> {
+        let now = unix_time();
+
+        Ok(Self {
+            source: source.to_string(),
+            source_hash: hash_file(source)?,
+            options,
+            transform,
+            settings,
+            stats: JobStats {
+                lines_total,
+                started_at: now,
+                updated_at: now,
+                ..JobStats::default()
+            },
+            finished: false
+        })
+    }
+
+    pub fn parse_from_file(path : &str) -> Result<Self, syact::Error> {
+        Ok(serde_json::from_str::<Self>(
+            std::fs::read_to_string(path)?.as_str()
+        )?)
+    }
+
+    /// Checks that the job can be resumed with the source file in its current state
+    pub fn verify(&self) -> Result<(), syact::Error> {
+        if self.finished {
+            return Err(JournalError::NothingToResume.into());
+        }
+
+        let hash = hash_file(&self.source)?;
+
+        if hash != self.source_hash {
+            return Err(JournalError::SourceChanged(self.source_hash.clone(), hash).into());
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the drawing loaded for resuming matches the job
+    pub fn verify_lines(&self, line_count : usize) -> Result<(), JournalError> {
+        if line_count != self.stats.lines_total {
+            return Err(JournalError::LineCountMismatch(self.stats.lines_total, line_count));
+        }
+
+        Ok(())
+    }
+}
+
+/// A job journal together with the file it is written to
+pub struct Journal {
+    pub path : String,
+    pub job : JobJournal
+}
+
+impl Journal {
+    /// Creates the journal of a new job, replacing the journal of the previous job
+    pub fn create(path : &str, job : JobJournal) -> Result<Self, syact::Error> {
+        let journal = Self { path: path.to_string(), job };
+        journal.save()?;
+        Ok(journal)
+    }
+
+    pub fn load(path : &str) -> Result<Self, syact::Error> {
+        Ok(Self { path: path.to_string(), job: JobJournal::parse_from_file(path)? })
+    }
+
+    /// Writes the journal into a temporary file first, so the old journal survives if the controller dies while writing
+    pub fn save(&self) -> Result<(), syact::Error> {
+        if let Some(dir) = std::path::Path::new(&self.path).parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let temp_path = format!("{}.tmp", self.path);
+        std::fs::write(&temp_path, serde_json::to_string_pretty(&self.job)?)?;
+        std::fs::rename(temp_path, &self.path)?;
+
+        Ok(())
+    }
+
+    /// Records the given amount of lines with the given length (mm) as drawn
+    pub fn checkpoint(&mut self, lines : usize, distance : f32) -> Result<(), syact::Error> {
+        self.job.stats.lines_done += lines;
+        self.job.stats.draw_distance += distance;
+        self.job.stats.updated_at = unix_time();
+        self.save()
+    }
+
+    pub fn finish(&mut self) -> Result<(), syact::Error> {
+        self.job.finished = true;
+        self.job.stats.updated_at = unix_time();
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a source file into a fresh temporary directory, returning the paths of the source and the journal
+    fn temp_job(name : &str, source : &str) -> (String, String) {
+        let dir = std::env::temp_dir().join(format!("drake_journal_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let source_path = dir.join("drawing.json");
+        std::fs::write(&source_path, source).unwrap();
+
+        (source_path.to_string_lossy().to_string(), dir.join(JOURNAL_FILE).to_string_lossy().to_string())
+    }
+
+    fn new_journal(source : &str, path : &str, lines_total : usize) -> Journal {
+        let job = JobJournal::new(source, DrawingOptions::default(), DrawingTransform::default(), JobSettings::default(), lines_total).unwrap();
+        Journal::create(path, job).unwrap()
+    }
+
+    #[test]
+    fn checkpoints_are_saved() {
+        let (source, path) = temp_job("checkpoint", "{ \"contour\": [] }");
+        let mut journal = new_journal(&source, &path, 25);
+
+        journal.checkpoint(10, 12.5).unwrap();
+        journal.checkpoint(3, 2.5).unwrap();
+
+        let loaded = Journal::load(&path).unwrap();
+        assert_eq!(loaded.job.stats.lines_done, 13);
+        assert_eq!(loaded.job.stats.draw_distance, 15.0);
+        assert_eq!(loaded.job.stats.lines_total, 25);
+        assert!(!loaded.job.finished);
+
+        // No temporary file is left behind
+        assert!(!std::path::Path::new(&format!("{}.tmp", path)).exists());
+
+        journal.finish().unwrap();
+        assert!(Journal::load(&path).unwrap().job.finished);
+    }
+
+    #[test]
+    fn resume_checks() {
+        let (source, path) = temp_job("resume", "{ \"contour\": [] }");
+        let mut journal = new_journal(&source, &path, 25);
+        journal.checkpoint(10, 12.5).unwrap();
+
+        let loaded = Journal::load(&path).unwrap();
+        loaded.job.verify().unwrap();
+        loaded.job.verify_lines(25).unwrap();
+        assert!(matches!(loaded.job.verify_lines(24), Err(JournalError::LineCountMismatch(25, 24))));
+
+        // Changing the source invalidates the job
+        std::fs::write(&source, "{ \"contour\": [ ] }").unwrap();
+        let err = loaded.job.verify().unwrap_err();
+        assert!(matches!(err.downcast_ref::<JournalError>(), Some(JournalError::SourceChanged(_, _))), "{}", err);
+
+        // A finished job cannot be resumed
+        journal.finish().unwrap();
+        let err = Journal::load(&path).unwrap().job.verify().unwrap_err();
+        assert!(matches!(err.downcast_ref::<JournalError>(), Some(JournalError::NothingToResume)), "{}", err);
+    }
+}
@@ -1,12 +1,20 @@
-use rppal::gpio::{Gpio, OutputPin};
-use rppal::i2c::I2c;
 use syact::meas::take_simple_meas;
 use syact::prelude::*;
 use sybot::prelude::*;
 
 use crate::config::{DrakeConfig, DrakeHardware};
+use crate::hal::{Hal, HalOutputPin};
+use crate::halt::{HaltError, HaltReport, HaltSignal};
+use crate::drawing::Drawing;
+use crate::drawing::transform::DrawingTransform;
+use crate::job::{JobSpeeds, PauseSignal};
+use crate::limits::{HardLimits, LimitError, LimitSwitch, SoftLimits};
+use crate::motion::{axis_factor, move_block, move_linear, plan_polyline, PlannerConfig, SpeedOverride};
+use crate::pen_lift::PenLift;
 use crate::servo_table::ServoTable;
-use crate::user_terminal::UserTerminal;
+use crate::stepping::XyStepper;
+use crate::toolpath::{MoveKind, Toolpath};
+use crate::user_terminal::{ButtonMonitor, UserTerminal};
 
 // Submodules
     pub mod config;
@@ -15,24 +23,98 @@ use crate::user_terminal::UserTerminal;
 
     pub mod drawing;
 
+    pub mod hal;
+
+    pub mod halt;
+
+    pub mod job;
+
+    pub mod journal;
+
+    pub mod limits;
+
+    pub mod menu;
+
+    pub mod motion;
+
+    pub mod pen_lift;
+
+    pub mod queue;
+
     pub mod routines;
 
     pub mod servo_table;
 
+    pub mod sim;
+
+    pub mod stepping;
+
+    pub mod toolpath;
+
     pub mod user_terminal;
 // 
 
+/// Input level at which the end switches are considered triggered
+pub const END_SWITCH_TRIGGER : bool = false;
+
 // Robots
     #[derive(StepperActuatorGroup)]
     pub struct DrakeComponents {
-        pub x : LinearAxis<ComplexStepper<OutputPin, OutputPin>>,
-        pub y : LinearAxis<ComplexStepper<OutputPin, OutputPin>>,
-        pub z : LinearAxis<ComplexStepper<OutputPin, OutputPin>>
+        pub x : LinearAxis<ComplexStepper<HalOutputPin, HalOutputPin>>,
+        pub y : LinearAxis<ComplexStepper<HalOutputPin, HalOutputPin>>,
+        pub z : LinearAxis<ComplexStepper<HalOutputPin, HalOutputPin>>
     }
 
     pub type DrakeRobot = StepperRobot<DrakeComponents, dyn StepperActuator, 3>;
 
-    pub fn drake_robot_new(hw : &DrakeHardware, config : &DrakeConfig, gpio : &Gpio) -> Result<DrakeRobot, syact::Error> {
+    /// Creates the end switches of an axis, the second (optional) one only stopping the axis in normal moves
+    fn axis_switches(hal : &Hal, limits : &HardLimits, axis : usize, meas : (u8, Direction), other : Option<u8>) 
+    -> Result<Vec<Box<dyn Interruptor + Send>>, syact::Error> {
+        let (meas_pin, meas_dir) = meas;
+
+        let mut switches : Vec<Box<dyn Interruptor + Send>> = vec![
+            Box::new(LimitSwitch::new(END_SWITCH_TRIGGER, meas_dir, hal.input(meas_pin)?, axis, limits)),
+            Box::new(limits.guard())
+        ];
+
+        if let Some(pin) = other {
+            let dir = if meas_dir == Direction::CW { Direction::CCW } else { Direction::CW };
+            switches.push(Box::new(LimitSwitch::new(END_SWITCH_TRIGGER, dir, hal.input(pin)?, axis, limits)));
+        }
+
+        Ok(switches)
+    }
+
+    /// Creates the robot, its end switches and halt guards reporting to the given station
+    pub fn drake_robot_new(hw : &DrakeHardware, config : &DrakeConfig, hal : &Hal, stat : &DrakeStation) -> Result<DrakeRobot, syact::Error> {
+        let limits = &stat.hard_limits;
+
+        let mut x_stepper = ComplexStepper::new(
+            GenericPWM::new(
+                hal.output(hw.x_step)?, 
+                hal.output(hw.x_dir)?
+            )?, 
+            StepperConst::MOT_17HE15_1504S
+        )?;
+        let mut y_stepper = ComplexStepper::new(GenericPWM::new(hal.output(hw.y_step)?, hal.output(hw.y_dir)?)?, StepperConst::MOT_17HE15_1504S)?;
+        let mut z_stepper = ComplexStepper::new(GenericPWM::new(hal.output(hw.z_step)?, hal.output(hw.z_dir)?)?, StepperConst::MOT_17HE15_1504S)?;
+
+        for switch in axis_switches(hal, limits, 0, (hw.x_meas_pos, Direction::CW), hw.x_meas_neg)? {
+            x_stepper.add_interruptor(switch);
+        }
+
+        for switch in axis_switches(hal, limits, 1, (hw.y_meas_pos, Direction::CW), hw.y_meas_neg)? {
+            y_stepper.add_interruptor(switch);
+        }
+
+        for switch in axis_switches(hal, limits, 2, (hw.z_meas_neg, Direction::CCW), hw.z_meas_pos)? {
+            z_stepper.add_interruptor(switch);
+        }
+
+        x_stepper.add_interruptor(Box::new(stat.halt.guard()));
+        y_stepper.add_interruptor(Box::new(stat.halt.guard()));
+        z_stepper.add_interruptor(Box::new(stat.halt.guard()));
+
         let mut rob = DrakeRobot::new([
             AngleConfig {
                 offset: Delta::ZERO,
@@ -47,33 +129,9 @@ use crate::user_terminal::UserTerminal;
                 counter: false
             }
         ], DrakeComponents {
-            x: LinearAxis::new(
-                ComplexStepper::new(
-                        GenericPWM::new(
-                            gpio.get(hw.x_step)?.into_output(), 
-                            gpio.get(hw.x_dir)?.into_output()
-                        )?, 
-                        StepperConst::MOT_17HE15_1504S
-                    )?
-                    .add_interruptor_inline(Box::new(
-                        EndSwitch::new(false, Some(Direction::CW), gpio.get(hw.x_meas_pos)?.into_input())
-                    ))
-                , config.ratio_x
-            ),
-            y: LinearAxis::new(
-                ComplexStepper::new(GenericPWM::new(gpio.get(hw.y_step).unwrap().into_output(), gpio.get(hw.y_dir).unwrap().into_output()).unwrap(), StepperConst::MOT_17HE15_1504S)?
-                    .add_interruptor_inline(Box::new(
-                        EndSwitch::new(false, Some(Direction::CW), gpio.get(hw.y_meas_pos).unwrap().into_input())
-                    ))
-                , config.ratio_y
-            ),
-            z: LinearAxis::new(
-                ComplexStepper::new(GenericPWM::new(gpio.get(hw.z_step).unwrap().into_output(), gpio.get(hw.z_dir).unwrap().into_output()).unwrap(), StepperConst::MOT_17HE15_1504S)? 
-                    .add_interruptor_inline(Box::new(
-                        EndSwitch::new(false, Some(Direction::CCW), gpio.get(hw.z_meas_neg).unwrap().into_input())
-                    ))
-                , config.ratio_z
-            )
+            x: LinearAxis::new(x_stepper, config.ratio_x),
+            y: LinearAxis::new(y_stepper, config.ratio_y),
+            z: LinearAxis::new(z_stepper, config.ratio_z)
         }, Vec::new());
 
         rob.comps_mut().set_micro([
@@ -95,16 +153,76 @@ use crate::user_terminal::UserTerminal;
         pub meas_data_y : SimpleMeasParams,
         pub meas_data_z : SimpleMeasParams,
 
+        /// Moves the pen between drawing and travel position
+        pub pen_lift : PenLift,
+
         // Values
-        pub z_lift : Delta
+        /// Positions the axes may never be moved beyond
+        pub limits : SoftLimits,
+        /// End switches tripped during normal moves, shared with the robot
+        pub hard_limits : HardLimits,
+        /// Halt requests of the halt button, shared with the robot
+        pub halt : HaltSignal,
+        /// Pause state of the current job
+        pub pause : PauseSignal,
+        /// Size of the paper starting at the drawing origin (mm)
+        pub paper_size : Option<[f32; 2]>,
+        /// Speeds of the axes at full speed factor (mm/s)
+        pub max_speed : [f32; 3],
+        /// Limits of the look-ahead planning of drawing moves
+        pub planner : PlannerConfig,
+        /// Step generator driving the X- and Y-axis along planned moves
+        pub stepper : XyStepper,
+        /// Pen speeds of the current job
+        pub speeds : JobSpeeds,
+        /// Live speed override of the current job, scaling all pen speeds
+        pub speed_override : SpeedOverride,
+
+        /// Records all pen moves if set
+        pub toolpath : Option<Toolpath>
     }
 
     impl DrakeStation {
-        pub fn new(hw : &DrakeHardware, config : &DrakeConfig, gpio : &Gpio, i2c : I2c) -> Result<Self, syact::Error> {
+        pub fn new(hw : &DrakeHardware, config : &DrakeConfig, hal : &Hal) -> Result<Self, syact::Error> {
+            let limits = SoftLimits::from_config(config);
+            let pen_lift = PenLift::from_config(config);
+
+            // Both pen heights have to be reachable
+            if let Some(height) = pen_lift.travel_height() {
+                limits.check_axis(2, height)?;
+            }
+
+            if let Some(height) = pen_lift.draw_height() {
+                limits.check_axis(2, height)?;
+            }
+
+            let hard_limits = HardLimits::new();
+            let halt = HaltSignal::new();
+
+            // Distance of a single microstep, the ratios are given in mm per radian
+            let step_angle = |micro : MicroSteps| 2.0 * core::f32::consts::PI / (StepperConst::MOT_17HE15_1504S.number_steps as f32 * micro.as_u8() as f32);
+
+            let mut stepper = XyStepper::new(
+                [ hal.output(hw.x_step)?, hal.output(hw.y_step)? ],
+                [ hal.output(hw.x_dir)?, hal.output(hw.y_dir)? ],
+                [ step_angle(hw.x_microsteps) * config.ratio_x, step_angle(hw.y_microsteps) * config.ratio_y ]
+            );
+
+            for switch in axis_switches(hal, &hard_limits, 0, (hw.x_meas_pos, Direction::CW), hw.x_meas_neg)? {
+                stepper.add_interruptor(0, switch);
+            }
+
+            for switch in axis_switches(hal, &hard_limits, 1, (hw.y_meas_pos, Direction::CW), hw.y_meas_neg)? {
+                stepper.add_interruptor(1, switch);
+            }
+
+            stepper.add_interruptor(0, Box::new(halt.guard()));
+            stepper.add_interruptor(1, Box::new(halt.guard()));
+
             Ok(Self {
-                servo_table: ServoTable::new(i2c)?, 
+                servo_table: ServoTable::from_config(hal, config)?, 
                 user_terminal: UserTerminal::new(
-                    gpio,
+                    hal,
                     hw.ut_start_switch,
                     hw.ut_start_led,
                     hw.ut_halt_switch,
@@ -118,24 +236,269 @@ use crate::user_terminal::UserTerminal;
                 meas_data_y: config.meas_data_y.clone(),
                 meas_data_z: config.meas_data_z.clone(),
 
-                z_lift: config.z_lift
+                pen_lift,
+
+                limits,
+                hard_limits,
+                halt,
+                pause: PauseSignal::new(),
+                paper_size: config.paper_size,
+                max_speed: [ config.max_speed_x, config.max_speed_y, config.max_speed_z ],
+                planner: PlannerConfig::from_config(config),
+                stepper,
+                speeds: JobSpeeds::from_config(config),
+                speed_override: SpeedOverride::default(),
+
+                toolpath: None
             })
         }
+
+        /// Current XY-position of the pen relative to the drawing origin
+        pub fn pen_pos(&self, rob : &DrakeRobot) -> [f32; 2] {
+            let gammas = rob.gammas();
+            [ gammas[0].0 - self.drawing_origin[0].0, gammas[1].0 - self.drawing_origin[1].0 ]
+        }
+
+        fn record(&mut self, kind : MoveKind, from : [f32; 2], to : [f32; 2]) {
+            if let Some(toolpath) = &mut self.toolpath {
+                toolpath.push(kind, from, to);
+            }
+        }
         
-        pub async fn reposition_pen(&self, rob : &mut DrakeRobot, point : [Phi; 2]) -> Result<(), syact::Error> {
-            rob.comps_mut().z.drive_rel(self.z_lift, Factor::MAX).await?;
-            rob.comps_mut().x.drive_abs(Gamma(point[0].0 + self.drawing_origin[0].0), Factor::MAX).await?;
-            rob.comps_mut().y.drive_abs(Gamma(point[1].0 + self.drawing_origin[1].0), Factor::MAX).await?;
-            rob.comps_mut().z.drive_rel(-self.z_lift, Factor::MAX).await?;
+        async fn measure(&mut self, rob : &mut DrakeRobot) -> Result<(), syact::Error> {
+            let meas_x = take_simple_meas(&mut rob.comps_mut().x, &self.meas_data_x, Factor::MAX).await?;
+            log::debug!("| > Measurement X: {:?}", meas_x);
+
+            let meas_y = take_simple_meas(&mut rob.comps_mut().y, &self.meas_data_y, Factor::MAX).await?;
+            log::debug!("| > Measurement Y: {:?}", meas_y);
+
+            let meas_z = take_simple_meas(&mut rob.comps_mut().z, &self.meas_data_z, Factor::MAX).await?;
+            log::debug!("| > Measurement Z: {:?}", meas_z);
+
             Ok(())
         }
+
+        /// Starts watching the halt button, until the returned monitor is dropped
+        pub fn spawn_halt_monitor(&self) -> ButtonMonitor {
+            let halt = self.halt.clone();
+
+            ButtonMonitor::spawn(self.user_terminal.halt_switch(), move || {
+                log::warn!("> Halt button pressed!");
+                halt.request();
+            })
+        }
+
+        /// Starts watching the start button, every press pausing or resuming the job, until the returned monitor is dropped
+        pub fn spawn_pause_monitor(&self) -> ButtonMonitor {
+            let pause = self.pause.clone();
+
+            ButtonMonitor::spawn(self.user_terminal.start_switch(), move || {
+                if pause.toggle() {
+                    log::info!("> Pausing after the next segment ... ");
+                } else {
+                    log::info!("> Resuming ... ");
+                }
+            })
+        }
+
+        /// Turns the interrupts of a move into their errors, a halt taking precedence over a tripped switch and both over the result of the move
+        pub(crate) async fn handle_interrupts(&mut self, rob : &mut DrakeRobot, result : Result<(), syact::Error>) -> Result<(), syact::Error> {
+            if self.halt.take() {
+                return Err(self.halt_stop(rob).await.into());
+            }
+
+            self.hard_limits.check()?;
+            result
+        }
+
+        /// Brings the robot into a safe state after a halt: pen lifted, servo table open
+        async fn halt_stop(&mut self, rob : &mut DrakeRobot) -> HaltError {
+            log::warn!("> Halting, lifting the pen and opening the table ... ");
+
+            let factor = self.lift_factor();
+
+            if let Err(err) = self.pen_lift.up(rob, &mut self.servo_table, factor).await {
+                log::error!("| > Lifting the pen failed: {}", err);
+                self.pen_lift.invalidate();
+            }
+
+            // The lift itself has been interrupted by another press
+            if self.halt.take() {
+                self.pen_lift.invalidate();
+            }
+
+            let table_open = self.servo_table.set_all_open().is_ok();
+
+            HaltError::Halted(HaltReport {
+                gammas: rob.gammas(),
+                pen: self.pen_lift.state(),
+                table_open
+            })
+        }
+
+        fn lift_factor(&self) -> Factor {
+            axis_factor(self.speeds.lift * self.speed_override.factor(), self.max_speed[2])
+        }
+
+        /// Moves the pen into the travel position
+        pub async fn lift_pen(&mut self, rob : &mut DrakeRobot) -> Result<(), syact::Error> {
+            let factor = self.lift_factor();
+            let result = self.pen_lift.up(rob, &mut self.servo_table, factor).await;
+
+            self.handle_interrupts(rob, result).await
+        }
+
+        /// Moves the pen into the drawing position
+        pub async fn lower_pen(&mut self, rob : &mut DrakeRobot) -> Result<(), syact::Error> {
+            let factor = self.lift_factor();
+            let result = self.pen_lift.down(rob, &mut self.servo_table, factor).await;
+
+            self.handle_interrupts(rob, result).await
+        }
+
+        /// Absolute XY-position of a point relative to the drawing origin
+        fn abs_pos(&self, point : [Phi; 2]) -> [f32; 2] {
+            [ point[0].0 + self.drawing_origin[0].0, point[1].0 + self.drawing_origin[1].0 ]
+        }
+
+        /// Checks all segments the drawing is expected to produce against the soft limits and the paper
+        pub fn check_drawing(&self, drawing : &Drawing, transform : &DrawingTransform) -> Result<(), LimitError> {
+            self.limits.check_drawing(drawing, transform, [ self.drawing_origin[0].0, self.drawing_origin[1].0 ], self.paper_size)
+        }
+
+        /// Lifts the pen, travels to the given point (relative to the drawing origin) and lowers the pen again
+        pub async fn reposition_pen(&mut self, rob : &mut DrakeRobot, point : [Phi; 2]) -> Result<(), syact::Error> {
+            let target = self.abs_pos(point);
+            self.limits.check_xy(target)?;
+            self.handle_interrupts(rob, Ok(())).await?;
+
+            let from = self.pen_pos(rob);
+
+            self.lift_pen(rob).await?;
+            let result = move_linear(
+                rob, 
+                &mut self.stepper,
+                [ Gamma(target[0]), Gamma(target[1]) ], 
+                self.speeds.travel * self.speed_override.factor(), 
+                &self.planner
+            ).await;
+
+            self.handle_interrupts(rob, result).await?;
+
+            self.lower_pen(rob).await?;
+
+            let to = self.pen_pos(rob);
+            self.record(MoveKind::Travel, from, to);
+            Ok(())
+        }
+
+        /// Draws a straight line from the current pen position to the given point (relative to the drawing origin) at the given speed (mm/s),
+        /// scaled by the speed override
+        pub async fn draw_to(&mut self, rob : &mut DrakeRobot, point : [Phi; 2], speed : f32) -> Result<(), syact::Error> {
+            self.draw_polyline(rob, &[ point ], speed).await
+        }
+
+        /// Lifts the pen and moves the head to the XY-position of home, out of the way of the paper
+        pub async fn park(&mut self, rob : &mut DrakeRobot) -> Result<(), syact::Error> {
+            let target = [ self.home[0].0, self.home[1].0 ];
+            self.limits.check_xy(target)?;
+
+            self.lift_pen(rob).await?;
+
+            let result = move_linear(
+                rob, 
+                &mut self.stepper,
+                [ Gamma(target[0]), Gamma(target[1]) ], 
+                self.speeds.travel * self.speed_override.factor(), 
+                &self.planner
+            ).await;
+
+            self.handle_interrupts(rob, result).await
+        }
+
+        /// Draws a polyline from the current pen position through all the given points (relative to the drawing origin)
+        /// 
+        /// The speeds are planned ahead, limiting the pen to what the corners and the end of the polyline allow, the pen passes
+        /// the points without stopping. The pen is lowered first if it is not down yet.
+        /// 
+        /// The steps are generated on the current thread, which requires a multi-threaded tokio runtime.
+        pub async fn draw_polyline(&mut self, rob : &mut DrakeRobot, points : &[[Phi; 2]], speed : f32) -> Result<(), syact::Error> {
+            self.draw_polyline_until(rob, points, speed, &|| false, &mut |_| Ok(())).await.map(|_| ())
+        }
+
+        /// Like `draw_polyline()`, but stops early once `stop` returns true, returning the amount of points reached
+        /// 
+        /// `stop` is checked before every segment. Once it returns true, the pen brakes as hard as allowed, stopping at the end of the
+        /// first segment it can. `progress` is called with the amount of points reached after every segment.
+        pub async fn draw_polyline_until(&mut self, rob : &mut DrakeRobot, points : &[[Phi; 2]], speed : f32, stop : &dyn Fn() -> bool, 
+            progress : &mut dyn FnMut(usize) -> Result<(), syact::Error>) -> Result<usize, syact::Error> {
+            if points.is_empty() {
+                return Ok(0);
+            }
+
+            let gammas = rob.gammas();
+
+            let mut path = vec![ [ gammas[0].0, gammas[1].0 ] ];
+            path.extend(points.iter().map(|&p| self.abs_pos(p)));
+
+            // Refuse the whole polyline before the first move
+            for &point in &path[1..] {
+                self.limits.check_xy(point)?;
+            }
+
+            self.handle_interrupts(rob, Ok(())).await?;
+
+            self.lower_pen(rob).await?;
+
+            let planned_override = self.speed_override.factor();
+            let mut reached = 0;
+
+            let mut braking = false;
+            let mut entry_speed = 0.0;
+
+            for mut block in plan_polyline(&path, speed * planned_override, &self.planner) {
+                braking |= stop();
+
+                if braking {
+                    // Brake from the speed actually reached
+                    block.entry_speed = entry_speed;
+                    block.exit_speed = block.exit_speed.min((entry_speed.powi(2) - 2.0 * block.accel * block.length).max(0.0).sqrt());
+                }
+
+                entry_speed = block.exit_speed;
+
+                let from = self.pen_pos(rob);
+
+                let result = move_block(rob, &mut self.stepper, &block, &self.speed_override, planned_override).await;
+
+                self.handle_interrupts(rob, result).await?;
+
+                let to = self.pen_pos(rob);
+                self.record(MoveKind::Draw, from, to);
+
+                // The path starts with the current position, so the end index of the block is the amount of points reached
+                reached = block.end;
+                progress(reached)?;
+
+                if braking && (block.exit_speed == 0.0) {
+                    return Ok(reached);
+                }
+            }
+
+            // Trailing points without distance are reached without a move
+            if reached < points.len() {
+                progress(points.len())?;
+            }
+
+            Ok(points.len())
+        }
     }
 
     impl Setup for DrakeStation {
         fn setup(&mut self) -> Result<(), syact::Error> {
             self.servo_table.setup()?;
             self.user_terminal.setup()?;
-            
+
             Ok(())
         }
     }
@@ -148,9 +511,14 @@ use crate::user_terminal::UserTerminal;
 
             log::info!("> Starting to calibrate ... ");
 
-            dbg!(take_simple_meas(&mut rob.comps_mut().x, &self.meas_data_x, Factor::MAX).await?);
-            dbg!(take_simple_meas(&mut rob.comps_mut().y, &self.meas_data_y, Factor::MAX).await?);
-            dbg!(take_simple_meas(&mut rob.comps_mut().z, &self.meas_data_z, Factor::MAX).await?);
+            // Driving into the end switches is expected while measuring, homing also clears all earlier trips and halts
+            self.hard_limits.set_measuring(true);
+            self.hard_limits.reset();
+            self.halt.take();
+
+            let result = self.measure(rob).await;
+            self.hard_limits.set_measuring(false);
+            result?;
 
             log::info!("> Calibration done! {:?}", rob.gammas());
 
@@ -161,16 +529,22 @@ use crate::user_terminal::UserTerminal;
             self.servo_table.set_all_open()?;
 
             self.calibrate(rob).await?;
+            self.pen_lift.invalidate();
 
             log::info!("Driving to home position ... ");
 
-            dbg!(rob.comps_mut().z.drive_abs(Gamma(self.home[2].0), Factor::MAX).await)?;   
-            dbg!(rob.comps_mut().x.drive_abs(Gamma(self.home[0].0), Factor::new(0.6)).await)?;
-            dbg!(rob.comps_mut().y.drive_abs(Gamma(self.home[1].0), Factor::MAX).await)?;
+            rob.comps_mut().z.drive_abs(Gamma(self.home[2].0), Factor::MAX).await?;
+            log::debug!("| > Z at home: {:?}", rob.comps().z.gamma());
+
+            rob.comps_mut().x.drive_abs(Gamma(self.home[0].0), Factor::new(0.6)).await?;
+            log::debug!("| > X at home: {:?}", rob.comps().x.gamma());
+
+            rob.comps_mut().y.drive_abs(Gamma(self.home[1].0), Factor::MAX).await?;
+            log::debug!("| > Y at home: {:?}", rob.comps().y.gamma());
 
             log::info!(" -> Driving to home done!");
 
-            self.servo_table.roll_servos(1.0)?;
+            self.servo_table.roll_servos(1.0).await?;
 
             Ok(())
         }
@@ -0,0 +1,378 @@
+use core::fmt::Display;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use embedded_hal::digital::InputPin;
+use syact::prelude::*;
+
+use crate::config::DrakeConfig;
+use crate::drawing::{Drawing, LinesFile};
+use crate::drawing::transform::DrawingTransform;
+use crate::toolpath::{MoveKind, Toolpath};
+
+/// Maximum amount of offending segments listed in an error message
+pub const MAX_LISTED_SEGMENTS : usize = 20;
+
+/// Names of the axes used in messages
+pub const AXIS_NAMES : [char; 3] = [ 'X', 'Y', 'Z' ];
+
+// Errors
+    /// Area a segment of a drawing leaves
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Boundary {
+        /// The soft limits of the axis with the given index
+        SoftLimit(usize),
+        /// The paper, spanning from the drawing origin to the paper size
+        Paper
+    }
+
+    impl Display for Boundary {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::SoftLimit(axis) => f.write_fmt(format_args!("{}-limits", AXIS_NAMES[*axis])),
+                Self::Paper => f.write_str("paper")
+            }
+        }
+    }
+
+    /// A segment of a drawing leaving the allowed area (mm, relative to the drawing origin)
+    #[derive(Clone, Debug)]
+    pub struct SegmentViolation {
+        /// Index of the move in the toolpath of the drawing
+        pub index : usize,
+        pub kind : MoveKind,
+        pub from : [f32; 2],
+        pub to : [f32; 2],
+        pub boundaries : Vec<Boundary>
+    }
+
+    impl Display for SegmentViolation {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let boundaries : Vec<String> = self.boundaries.iter().map(|b| b.to_string()).collect();
+
+            f.write_fmt(format_args!("#{} {} ({:.2}, {:.2}) -> ({:.2}, {:.2}) leaves the {}",
+                self.index,
+                match self.kind { MoveKind::Draw => "draw", MoveKind::Travel => "travel" },
+                self.from[0], self.from[1], self.to[0], self.to[1],
+                boundaries.join(", ")
+            ))
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    pub enum LimitError {
+        /// The axis with the given index would be moved to the given position outside of its limits
+        OutOfLimits(usize, f32, [f32; 2]),
+        /// Segments of a drawing leave the limits or the paper
+        SegmentsOutOfBounds(Vec<SegmentViolation>),
+        /// The end switch of the axis with the given index in the given direction has been triggered outside of a measurement
+        LimitHit(usize, Direction)
+    }
+
+    impl Display for LimitError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::OutOfLimits(axis, pos, limits) => f.write_fmt(format_args!(
+                    "OutOfLimits: The position {} of the {}-axis is outside of its soft limits ({} - {})!", pos, AXIS_NAMES[*axis], limits[0], limits[1]
+                )),
+                Self::SegmentsOutOfBounds(segments) => {
+                    f.write_fmt(format_args!("SegmentsOutOfBounds: {} segments of the drawing are out of bounds!", segments.len()))?;
+
+                    for segment in segments.iter().take(MAX_LISTED_SEGMENTS) {
+                        f.write_fmt(format_args!("\n  {}", segment))?;
+                    }
+
+                    if segments.len() > MAX_LISTED_SEGMENTS {
+                        f.write_fmt(format_args!("\n  ... and {} more", segments.len() - MAX_LISTED_SEGMENTS))?;
+                    }
+
+                    Ok(())
+                },
+                Self::LimitHit(axis, dir) => f.write_fmt(format_args!(
+                    "LimitHit: The {} end switch of the {}-axis has been triggered, the robot has to be homed again!",
+                    if *dir == Direction::CW { "positive" } else { "negative" }, AXIS_NAMES[*axis]
+                ))
+            }
+        }
+    }
+
+    impl std::error::Error for LimitError { }
+//
+
+/// Software limits of the axes, in absolute positions (mm)
+#[derive(Clone, Debug, Default)]
+pub struct SoftLimits {
+    /// Minimum and maximum position of each axis, unlimited if `None`
+    pub axes : [Option<[f32; 2]>; 3]
+}
+
+impl SoftLimits {
+    pub fn from_config(config : &DrakeConfig) -> Self {
+        Self {
+            axes: [ config.limits_x, config.limits_y, config.limits_z ]
+        }
+    }
+
+    fn contains(&self, axis : usize, pos : f32) -> bool {
+        !matches!(self.axes[axis], Some([ min, max ]) if (pos < min) || (max < pos))
+    }
+
+    /// Checks whether the axis with the given index may be moved to the given position
+    pub fn check_axis(&self, axis : usize, pos : f32) -> Result<(), LimitError> {
+        match self.axes[axis] {
+            Some(limits) if !self.contains(axis, pos) => Err(LimitError::OutOfLimits(axis, pos, limits)),
+            _ => Ok(())
+        }
+    }
+
+    /// Checks a position of the X- and Y-axis
+    pub fn check_xy(&self, pos : [f32; 2]) -> Result<(), LimitError> {
+        self.check_axis(0, pos[0])?;
+        self.check_axis(1, pos[1])
+    }
+
+    /// Checks every segment a `LinesFile` is expected to produce, see `check_drawing()`
+    pub fn check_lines(&self, lines : &LinesFile, transform : &DrawingTransform, origin : [f32; 2], paper_size : Option<[f32; 2]>) -> Result<(), LimitError> {
+        self.check_drawing(&Drawing::from_lines(lines), transform, origin, paper_size)
+    }
+
+    /// Checks every segment the drawing is expected to produce against the limits of the X- and Y-axis and, if given, the paper
+    ///
+    /// As both areas are rectangles, a straight segment stays inside of them if both its ends do.
+    pub fn check_drawing(&self, drawing : &Drawing, transform : &DrawingTransform, origin : [f32; 2], paper_size : Option<[f32; 2]>) -> Result<(), LimitError> {
+        let toolpath = Toolpath::from_drawing(drawing, transform);
+        let mut violations = Vec::new();
+
+        for (index, m) in toolpath.moves.iter().enumerate() {
+            let mut boundaries = Vec::new();
+
+            for (axis, offset) in origin.iter().enumerate() {
+                if !self.contains(axis, m.from[axis] + offset) || !self.contains(axis, m.to[axis] + offset) {
+                    boundaries.push(Boundary::SoftLimit(axis));
+                }
+            }
+
+            // Only drawn lines have to stay on the paper
+            if let (Some(size), MoveKind::Draw) = (paper_size, m.kind) {
+                let on_paper = |p : [f32; 2]| (0.0 ..= size[0]).contains(&p[0]) && (0.0 ..= size[1]).contains(&p[1]);
+
+                if !on_paper(m.from) || !on_paper(m.to) {
+                    boundaries.push(Boundary::Paper);
+                }
+            }
+
+            if !boundaries.is_empty() {
+                violations.push(SegmentViolation { index, kind: m.kind, from: m.from, to: m.to, boundaries });
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(LimitError::SegmentsOutOfBounds(violations))
+        }
+    }
+}
+
+// Hard limits
+    #[derive(Default)]
+    struct HardLimitState {
+        measuring : AtomicBool,
+        /// Tripped switches of every axis (negative, positive)
+        tripped : [[AtomicBool; 2]; 3]
+    }
+
+    /// End switches triggered outside of measurements, shared between the switches of the robot and the station
+    ///
+    /// Once a switch has been triggered, all axes stop until the trip is reset by homing the robot.
+    #[derive(Clone, Default)]
+    pub struct HardLimits {
+        state : Arc<HardLimitState>
+    }
+
+    impl HardLimits {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// While measuring, triggered switches stop their axis without being recorded as trips
+        pub fn set_measuring(&self, measuring : bool) {
+            self.state.measuring.store(measuring, Ordering::Relaxed);
+        }
+
+        fn trip(&self, axis : usize, dir : Direction) {
+            if !self.state.measuring.load(Ordering::Relaxed) {
+                self.state.tripped[axis][dir.as_bool() as usize].store(true, Ordering::Relaxed);
+            }
+        }
+
+        /// The first tripped switch, as axis index and direction
+        pub fn tripped(&self) -> Option<(usize, Direction)> {
+            (0 .. 3).flat_map(|axis| [ (axis, Direction::CCW), (axis, Direction::CW) ])
+                .find(|&(axis, dir)| self.state.tripped[axis][dir.as_bool() as usize].load(Ordering::Relaxed))
+        }
+
+        pub fn reset(&self) {
+            for flag in self.state.tripped.iter().flatten() {
+                flag.store(false, Ordering::Relaxed);
+            }
+        }
+
+        /// Returns a `LimitHit` error if any switch has been tripped
+        pub fn check(&self) -> Result<(), LimitError> {
+            match self.tripped() {
+                Some((axis, dir)) => Err(LimitError::LimitHit(axis, dir)),
+                None => Ok(())
+            }
+        }
+
+        /// Interruptor stopping an axis in both directions while any switch is tripped
+        pub fn guard(&self) -> LimitGuard {
+            LimitGuard { limits: self.clone() }
+        }
+    }
+
+    /// An end switch stopping its axis when moving towards it, recording the trip in the shared `HardLimits`
+    pub struct LimitSwitch<P : InputPin> {
+        trigger : bool,
+        dir : Direction,
+        pin : P,
+
+        axis : usize,
+        limits : HardLimits
+    }
+
+    impl<P : InputPin> LimitSwitch<P> {
+        pub fn new(trigger : bool, dir : Direction, pin : P, axis : usize, limits : &HardLimits) -> Self {
+            Self { trigger, dir, pin, axis, limits: limits.clone() }
+        }
+    }
+
+    impl<P : InputPin> Interruptor for LimitSwitch<P> {
+        fn dir(&self) -> Option<Direction> {
+            Some(self.dir)
+        }
+
+        fn set_dir(&mut self, dir : Direction) {
+            self.dir = dir;
+        }
+
+        fn check(&mut self, _gamma : Gamma) -> Option<InterruptReason> {
+            // Pins that cannot be read count as triggered
+            let level = self.pin.is_high().unwrap_or(self.trigger);
+
+            if level == self.trigger {
+                self.limits.trip(self.axis, self.dir);
+                Some(InterruptReason::EndReached)
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Stops the axis it is attached to as soon as any end switch of the robot has been tripped
+    pub struct LimitGuard {
+        limits : HardLimits
+    }
+
+    impl Interruptor for LimitGuard {
+        fn dir(&self) -> Option<Direction> {
+            None
+        }
+
+        fn set_dir(&mut self, _dir : Direction) { }
+
+        fn check(&mut self, _gamma : Gamma) -> Option<InterruptReason> {
+            self.limits.tripped().map(|_| InterruptReason::EndReached)
+        }
+    }
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::drawing::DrawingUnit;
+
+    fn limits() -> SoftLimits {
+        SoftLimits { axes: [ Some([ 0.0, 100.0 ]), Some([ -10.0, 50.0 ]), None ] }
+    }
+
+    fn drawing(strokes : Vec<Vec<[f32; 2]>>) -> Drawing {
+        Drawing { strokes, unit: DrawingUnit::Millimeter }
+    }
+
+    fn violations(result : Result<(), LimitError>) -> Vec<SegmentViolation> {
+        match result {
+            Err(LimitError::SegmentsOutOfBounds(violations)) => violations,
+            other => panic!("Expected out of bounds segments, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn check_axis() {
+        let limits = limits();
+
+        assert!(limits.check_axis(0, 0.0).is_ok());
+        assert!(limits.check_axis(0, 100.0).is_ok());
+        assert!(limits.check_axis(1, -10.0).is_ok());
+        // Unlimited axis
+        assert!(limits.check_axis(2, -1000.0).is_ok());
+
+        assert!(matches!(limits.check_axis(0, -0.1), Err(LimitError::OutOfLimits(0, _, [ 0.0, 100.0 ]))));
+        assert!(matches!(limits.check_axis(1, 50.5), Err(LimitError::OutOfLimits(1, _, _))));
+        assert!(matches!(limits.check_xy([ 10.0, 60.0 ]), Err(LimitError::OutOfLimits(1, _, _))));
+        assert!(limits.check_xy([ 10.0, 40.0 ]).is_ok());
+    }
+
+    #[test]
+    fn drawing_in_range() {
+        let square = drawing(vec![ vec![ [ 0.0, 0.0 ], [ 40.0, 0.0 ], [ 40.0, 40.0 ], [ 0.0, 40.0 ], [ 0.0, 0.0 ] ] ]);
+
+        assert!(limits().check_drawing(&square, &DrawingTransform::default(), [ 10.0, 0.0 ], Some([ 40.0, 40.0 ])).is_ok());
+    }
+
+    #[test]
+    fn drawing_out_of_range() {
+        let lines = drawing(vec![
+            vec![ [ 10.0, 45.0 ], [ 10.0, 50.0 ] ],
+            vec![ [ 0.0, 0.0 ], [ 95.0, 0.0 ] ]
+        ]);
+        let transform = DrawingTransform::default();
+
+        // The last line leaves both the X-limits and the paper
+        let violations_x = violations(limits().check_drawing(&lines, &transform, [ 10.0, 0.0 ], Some([ 60.0, 60.0 ])));
+        assert_eq!(violations_x.len(), 1);
+        assert_eq!(violations_x[0].index, 2);
+        assert_eq!(violations_x[0].kind, MoveKind::Draw);
+        assert_eq!(violations_x[0].boundaries, vec![ Boundary::SoftLimit(0), Boundary::Paper ]);
+
+        // Only drawn lines have to stay on the paper, the travel from the first line is fine
+        let violations_paper = violations(limits().check_drawing(&lines, &transform, [ 0.0, 0.0 ], Some([ 100.0, 48.0 ])));
+        assert_eq!(violations_paper.len(), 1);
+        assert_eq!(violations_paper[0].index, 0);
+        assert_eq!(violations_paper[0].boundaries, vec![ Boundary::Paper ]);
+
+        // Travels have to stay within the limits
+        let violations_y = violations(limits().check_drawing(&lines, &transform, [ 0.0, 10.0 ], None));
+        assert_eq!(violations_y.len(), 2);
+        assert_eq!(violations_y[1].index, 1);
+        assert_eq!(violations_y[1].kind, MoveKind::Travel);
+        assert_eq!(violations_y[1].boundaries, vec![ Boundary::SoftLimit(1) ]);
+    }
+
+    #[test]
+    fn listed_violations_are_capped() {
+        let lines = drawing((0 .. 25).map(|i| vec![ [ i as f32, 0.0 ], [ i as f32, 60.0 ] ]).collect());
+        let error = limits().check_drawing(&lines, &DrawingTransform::default(), [ 0.0, 0.0 ], None).unwrap_err();
+
+        let message = error.to_string();
+        let violations = violations(Err(error));
+
+        // All lines and the travels between them leave the Y-limits
+        assert_eq!(violations.len(), 49);
+        assert!(message.starts_with("SegmentsOutOfBounds: 49 segments"));
+        assert_eq!(message.lines().count(), 1 + MAX_LISTED_SEGMENTS + 1);
+        assert!(message.ends_with("... and 29 more"));
+    }
+}
@@ -0,0 +1,320 @@
+use core::fmt::Display;
+use core::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::hal::HalInputPin;
+use crate::user_terminal::{Debouncer, LedPattern, UserTerminal, LONG_PRESS_TIME, WAIT_POLL_INTERVAL};
+
+/// Maximum time between the release of a button and the second press of a double press
+pub const DOUBLE_PRESS_TIME : Duration = Duration::from_millis(400);
+/// Time the user has to confirm a selected action with a short press of the start button
+pub const CONFIRM_TIME : Duration = Duration::from_secs(5);
+
+// Gestures
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Button {
+        Start,
+        Halt
+    }
+
+    impl Button {
+        fn index(&self) -> usize {
+            match self {
+                Self::Start => 0,
+                Self::Halt => 1
+            }
+        }
+
+        fn other(&self) -> Self {
+            match self {
+                Self::Start => Self::Halt,
+                Self::Halt => Self::Start
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Gesture {
+        Short(Button),
+        /// Two short presses within `DOUBLE_PRESS_TIME`
+        Double(Button),
+        /// Held down for `LONG_PRESS_TIME`
+        Long(Button),
+        /// Both buttons held down together for `LONG_PRESS_TIME`
+        Both
+    }
+
+    #[derive(Clone, Copy, Debug)]
+    enum GestureState {
+        Idle,
+        Pressed(Button, Instant),
+        /// Released after a short press, waiting for a second one
+        Released(Button, Instant),
+        Both(Instant),
+        /// Waiting for all buttons to be released after a gesture
+        Settle
+    }
+
+    /// Recognizes gestures of the start and the halt button of a `UserTerminal`
+    pub struct GestureReader {
+        switches : [Arc<Mutex<HalInputPin>>; 2],
+        debouncers : [Debouncer; 2],
+        state : GestureState
+    }
+
+    impl GestureReader {
+        pub fn new(user_terminal : &UserTerminal) -> Self {
+            Self {
+                switches: [ user_terminal.start_switch(), user_terminal.halt_switch() ],
+                debouncers: [ Debouncer::new(false), Debouncer::new(false) ],
+                // Buttons still held down from before do not start a gesture
+                state: GestureState::Settle
+            }
+        }
+
+        fn read(&mut self) -> [bool; 2] {
+            [ 0, 1 ].map(|index| {
+                let level = self.switches[index].lock().unwrap().is_high();
+                self.debouncers[index].update(level)
+            })
+        }
+
+        /// Advances the recognition with the debounced levels of both buttons read at the given time
+        fn step(&mut self, levels : [bool; 2], now : Instant) -> Option<Gesture> {
+            let (state, gesture) = match self.state {
+                GestureState::Settle =>
+                    (if levels == [ false, false ] { GestureState::Idle } else { GestureState::Settle }, None),
+
+                GestureState::Idle => match levels {
+                    [ true, true ] => (GestureState::Both(now), None),
+                    [ true, false ] => (GestureState::Pressed(Button::Start, now), None),
+                    [ false, true ] => (GestureState::Pressed(Button::Halt, now), None),
+                    [ false, false ] => (GestureState::Idle, None)
+                },
+
+                GestureState::Pressed(button, since) => {
+                    if levels[button.other().index()] {
+                        (GestureState::Both(now), None)
+                    } else if !levels[button.index()] {
+                        (GestureState::Released(button, now), None)
+                    } else if now.duration_since(since) >= LONG_PRESS_TIME {
+                        (GestureState::Settle, Some(Gesture::Long(button)))
+                    } else {
+                        (self.state, None)
+                    }
+                },
+
+                GestureState::Released(button, since) => {
+                    if levels[button.index()] {
+                        (GestureState::Settle, Some(Gesture::Double(button)))
+                    } else if levels[button.other().index()] {
+                        (GestureState::Pressed(button.other(), now), Some(Gesture::Short(button)))
+                    } else if now.duration_since(since) >= DOUBLE_PRESS_TIME {
+                        (GestureState::Idle, Some(Gesture::Short(button)))
+                    } else {
+                        (self.state, None)
+                    }
+                },
+
+                GestureState::Both(since) => {
+                    if levels != [ true, true ] {
+                        // Releasing a button early cancels the gesture
+                        (GestureState::Settle, None)
+                    } else if now.duration_since(since) >= LONG_PRESS_TIME {
+                        (GestureState::Settle, Some(Gesture::Both))
+                    } else {
+                        (self.state, None)
+                    }
+                }
+            };
+
+            self.state = state;
+            gesture
+        }
+
+        /// Waits for the next gesture, returning `None` if none started before the timeout
+        pub async fn next(&mut self, timeout : Option<Duration>) -> Option<Gesture> {
+            let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+            loop {
+                let levels = self.read();
+
+                if let Some(gesture) = self.step(levels, Instant::now()) {
+                    return Some(gesture);
+                }
+
+                // Gestures already started are always finished
+                if matches!(self.state, GestureState::Idle | GestureState::Settle) && deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    return None;
+                }
+
+                tokio::time::sleep(WAIT_POLL_INTERVAL).await;
+            }
+        }
+    }
+//
+
+/// Actions of the station that can be selected with the buttons
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuAction {
+    /// Start button double press
+    Home,
+    /// Start button long press
+    NextJob,
+    /// Halt button double press
+    TestTable,
+    /// Both buttons held down together
+    Shutdown
+}
+
+impl MenuAction {
+    pub fn from_gesture(gesture : Gesture) -> Option<Self> {
+        match gesture {
+            Gesture::Double(Button::Start) => Some(Self::Home),
+            Gesture::Long(Button::Start) => Some(Self::NextJob),
+            Gesture::Double(Button::Halt) => Some(Self::TestTable),
+            Gesture::Both => Some(Self::Shutdown),
+            _ => None
+        }
+    }
+
+    /// Amount of blinks identifying the action
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::Home => 1,
+            Self::NextJob => 2,
+            Self::TestTable => 3,
+            Self::Shutdown => 4
+        }
+    }
+}
+
+impl Display for MenuAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Home => "home",
+            Self::NextJob => "next job",
+            Self::TestTable => "table test",
+            Self::Shutdown => "shutdown"
+        })
+    }
+}
+
+/// Lets the user select actions with the buttons of the user terminal, so the station can run without a console
+///
+/// While waiting, the start LED blinks slowly. A selected action is shown as blink code on the start LED until
+/// it is confirmed by a short press of the start button, any other gesture or `CONFIRM_TIME` passing cancel it.
+pub struct Menu {
+    gestures : GestureReader
+}
+
+impl Menu {
+    pub fn new(user_terminal : &UserTerminal) -> Self {
+        Self { gestures: GestureReader::new(user_terminal) }
+    }
+
+    /// Waits until the user has selected and confirmed an action
+    pub async fn select(&mut self, user_terminal : &mut UserTerminal) -> MenuAction {
+        loop {
+            user_terminal.set_start_pattern(LedPattern::SlowBlink);
+
+            let Some(gesture) = self.gestures.next(None).await else {
+                continue;
+            };
+
+            let Some(action) = MenuAction::from_gesture(gesture) else {
+                log::debug!("| > Gesture {:?} has no action", gesture);
+                continue;
+            };
+
+            log::info!("| > Selected {}, press start to confirm ... ", action);
+            user_terminal.set_start_pattern(LedPattern::Code(action.code()));
+
+            let confirmation = self.gestures.next(Some(CONFIRM_TIME)).await;
+            user_terminal.set_start_led(false);
+
+            if confirmation == Some(Gesture::Short(Button::Start)) {
+                return action;
+            }
+
+            log::info!("| > Selection of {} cancelled", action);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::hal::Hal;
+
+    const START : [bool; 2] = [ true, false ];
+    const HALT : [bool; 2] = [ false, true ];
+    const BOTH : [bool; 2] = [ true, true ];
+    const NONE : [bool; 2] = [ false, false ];
+
+    fn reader() -> GestureReader {
+        let user_terminal = UserTerminal::new(&Hal::Virtual(Default::default()), 0, 1, 2, 3).unwrap();
+        let mut reader = GestureReader::new(&user_terminal);
+        reader.step(NONE, Instant::now());
+        reader
+    }
+
+    /// Feeds the levels at the given times (ms after the start), returning all gestures with the time they were recognized at
+    fn play(reader : &mut GestureReader, steps : &[(u64, [bool; 2])]) -> Vec<(u64, Gesture)> {
+        let start = Instant::now();
+
+        steps.iter().filter_map(|&(ms, levels)| {
+            reader.step(levels, start + Duration::from_millis(ms)).map(|gesture| (ms, gesture))
+        }).collect()
+    }
+
+    #[test]
+    fn short_press() {
+        let mut reader = reader();
+
+        // Reported once no second press follows within `DOUBLE_PRESS_TIME`
+        assert_eq!(play(&mut reader, &[ (0, START), (100, NONE), (400, NONE), (500, NONE) ]), vec![ (500, Gesture::Short(Button::Start)) ]);
+        assert_eq!(play(&mut reader, &[ (0, HALT), (100, NONE), (200, HALT) ]), vec![ (200, Gesture::Double(Button::Halt)) ]);
+    }
+
+    #[test]
+    fn long_press() {
+        let mut reader = reader();
+
+        // Reported while the button is still held down
+        assert_eq!(play(&mut reader, &[ (0, START), (500, START), (999, START), (1000, START) ]), vec![ (1000, Gesture::Long(Button::Start)) ]);
+    }
+
+    #[test]
+    fn both_buttons() {
+        let mut reader = reader();
+
+        assert_eq!(play(&mut reader, &[ (0, START), (50, BOTH), (1000, BOTH), (1050, BOTH) ]), vec![ (1050, Gesture::Both) ]);
+
+        // Releasing a button early cancels the gesture
+        assert_eq!(play(&mut reader, &[ (2000, NONE), (2100, BOTH), (2500, HALT), (4000, HALT), (4100, NONE) ]), vec![ ]);
+    }
+
+    #[test]
+    fn settles_after_gestures() {
+        let mut reader = reader();
+
+        // Holding the button after a long press does not start another gesture
+        let gestures = play(&mut reader, &[ (0, START), (1000, START), (3000, START), (5000, START) ]);
+        assert_eq!(gestures, vec![ (1000, Gesture::Long(Button::Start)) ]);
+
+        // Neither does releasing one of both buttons
+        let gestures = play(&mut reader, &[ (0, HALT), (1000, BOTH), (3000, START), (4000, NONE), (4100, START), (5100, START) ]);
+        assert_eq!(gestures, vec![ (5100, Gesture::Long(Button::Start)) ]);
+    }
+
+    #[test]
+    fn buttons_held_on_creation_are_ignored() {
+        let user_terminal = UserTerminal::new(&Hal::Virtual(Default::default()), 0, 1, 2, 3).unwrap();
+        let mut reader = GestureReader::new(&user_terminal);
+
+        assert_eq!(play(&mut reader, &[ (0, START), (2000, START), (2100, NONE), (2200, START), (3200, START) ]), vec![ (3200, Gesture::Long(Button::Start)) ]);
+    }
+}
@@ -0,0 +1,313 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use syact::prelude::*;
+use sybot::prelude::*;
+
+use crate::{DrakeComponents, DrakeRobot};
+use crate::config::DrakeConfig;
+use crate::stepping::XyStepper;
+
+/// Axis movements shorter than this distance are skipped (mm)
+pub const MOVE_EPSILON : f32 = 1e-4;
+
+/// Limits of the speed override (%)
+pub const SPEED_OVERRIDE_RANGE : (u32, u32) = (10, 200);
+
+/// Speed override percentage shared between a running job and its controls, scaling all pen speeds
+#[derive(Clone, Debug)]
+pub struct SpeedOverride {
+    percent : Arc<AtomicU32>
+}
+
+impl Default for SpeedOverride {
+    fn default() -> Self {
+        Self::new(100)
+    }
+}
+
+impl SpeedOverride {
+    pub fn new(percent : u32) -> Self {
+        let speed_override = Self { percent: Arc::new(AtomicU32::new(100)) };
+        speed_override.set_percent(percent);
+        speed_override
+    }
+
+    pub fn percent(&self) -> u32 {
+        self.percent.load(Ordering::Relaxed)
+    }
+
+    /// Sets the override, limited to `SPEED_OVERRIDE_RANGE`, returns the new value
+    pub fn set_percent(&self, percent : u32) -> u32 {
+        let percent = percent.clamp(SPEED_OVERRIDE_RANGE.0, SPEED_OVERRIDE_RANGE.1);
+        self.percent.store(percent, Ordering::Relaxed);
+        percent
+    }
+
+    /// Changes the override by the given amount of percent, returns the new value
+    pub fn adjust(&self, delta : i32) -> u32 {
+        self.set_percent((self.percent() as i32 + delta).max(0) as u32)
+    }
+
+    pub fn factor(&self) -> f32 {
+        self.percent() as f32 / 100.0
+    }
+}
+
+/// Speed factor of a single axis moving at the given speed (mm/s)
+pub fn axis_factor(speed : f32, max_speed : f32) -> Factor {
+    Factor::new((speed / max_speed).clamp(0.01, 1.0))
+}
+
+/// Moves the pen in a straight line to the given XY-position (absolute, mm), accelerating to the given speed (mm/s) and braking
+/// to a stop at the end
+pub async fn move_linear(rob : &mut DrakeRobot, stepper : &mut XyStepper, to : [Gamma; 2], speed : f32, planner : &PlannerConfig) 
+-> Result<(), syact::Error> {
+    let gammas = rob.gammas();
+    let speed_override = SpeedOverride::new(100);
+
+    for block in plan_polyline(&[ [ gammas[0].0, gammas[1].0 ], [ to[0].0, to[1].0 ] ], speed, planner) {
+        move_block(rob, stepper, &block, &speed_override, 1.0).await?;
+    }
+
+    Ok(())
+}
+
+// Planning
+    /// Share of the stall torque used for accelerating, as the torque of a stepper drops significantly with its speed
+    pub const ACCEL_TORQUE_FACTOR : f32 = 0.1;
+    /// Speed used for sections of a planned move where the profile speed reaches zero (mm/s)
+    pub const MIN_SPEED : f32 = 1.0;
+
+    /// Limits used for the look-ahead velocity planning
+    #[derive(Clone, Debug)]
+    pub struct PlannerConfig {
+        /// Maximum speed of the X- and Y-axis (mm/s)
+        pub max_speed : [f32; 2],
+        /// Maximum acceleration of the X- and Y-axis (mm/s²)
+        pub max_accel : [f32; 2],
+        /// Maximum deviation of the pen from the corner when passing it without stopping (mm)
+        pub junction_deviation : f32,
+        /// Amount of segments looked ahead, the pen has to be able to stop at the end of the window
+        pub lookahead : usize
+    }
+
+    impl PlannerConfig {
+        /// Derives the acceleration limits from the stall torque and inertia of the motors, the moved weights and the axis ratios,
+        /// unless they are given in the config
+        pub fn from_config(config : &DrakeConfig) -> Self {
+            let consts = StepperConst::MOT_17HE15_1504S;
+
+            // Ratios are given in mm per radian, weights in kg
+            let axis_accel = |ratio : f32, weight : Inertia| {
+                let inertia = consts.inertia_motor.0 + weight.0 * (ratio / 1000.0).powi(2);
+                consts.torque_stall.0 * ACCEL_TORQUE_FACTOR / inertia * ratio
+            };
+
+            Self {
+                max_speed: [ config.max_speed_x, config.max_speed_y ],
+                max_accel: config.max_accel.unwrap_or([ 
+                    axis_accel(config.ratio_x, config.weights[0]), 
+                    axis_accel(config.ratio_y, config.weights[1]) 
+                ]),
+                junction_deviation: config.junction_deviation,
+                lookahead: config.lookahead.max(1)
+            }
+        }
+    }
+
+    /// A straight segment of a planned move with a trapezoidal speed profile (mm, mm/s, mm/s²)
+    #[derive(Clone, Debug)]
+    pub struct Block {
+        pub from : [f32; 2],
+        pub to : [f32; 2],
+        /// Index of the point of the polyline the block ends at
+        pub end : usize,
+        pub length : f32,
+        /// Maximum acceleration along the segment
+        pub accel : f32,
+        pub entry_speed : f32,
+        pub cruise_speed : f32,
+        pub exit_speed : f32
+    }
+
+    impl Block {
+        /// Speed of the profile after the given distance from the start of the block
+        pub fn speed_at(&self, dist : f32) -> f32 {
+            let accel = (self.entry_speed.powi(2) + 2.0 * self.accel * dist).sqrt();
+            let decel = (self.exit_speed.powi(2) + 2.0 * self.accel * (self.length - dist).max(0.0)).sqrt();
+
+            accel.min(decel).min(self.cruise_speed)
+        }
+    }
+
+    /// Maximum value along the direction `dir` (unit vector) without exceeding the limit of any axis, used for speeds and accelerations
+    fn direction_limit(dir : [f32; 2], axis_limits : [f32; 2]) -> f32 {
+        let limit = |component : f32, axis_limit : f32| if component.abs() > MOVE_EPSILON { axis_limit / component.abs() } else { f32::INFINITY };
+        limit(dir[0], axis_limits[0]).min(limit(dir[1], axis_limits[1]))
+    }
+
+    /// Maximum speed to pass the corner between two segments with the given directions (unit vectors) without stopping
+    ///
+    /// Uses the junction deviation model: the corner is approximated by a circle touching both segments, whose distance to the
+    /// corner is the junction deviation. The centripetal acceleration on that circle may not exceed the acceleration limit.
+    pub fn junction_speed(dir_in : [f32; 2], dir_out : [f32; 2], accel : f32, junction_deviation : f32) -> f32 {
+        let cos_theta = -(dir_in[0] * dir_out[0] + dir_in[1] * dir_out[1]);
+
+        // Straight continuation
+        if cos_theta < -0.9999 {
+            return f32::INFINITY;
+        }
+
+        // Full reversal
+        if cos_theta > 0.9999 {
+            return 0.0;
+        }
+
+        let sin_half = ((1.0 - cos_theta) / 2.0).sqrt();
+        (accel * junction_deviation * sin_half / (1.0 - sin_half)).sqrt()
+    }
+
+    /// Plans the speed profiles of a polyline (mm) drawn with the given nominal speed (mm/s), starting and ending at standstill
+    ///
+    /// The profile of every block is planned over the window of the following `lookahead` blocks, with the pen being able to stop
+    /// at the end of the window.
+    pub fn plan_polyline(points : &[[f32; 2]], speed : f32, config : &PlannerConfig) -> Vec<Block> {
+        let mut blocks : Vec<Block> = Vec::new();
+        let mut dirs : Vec<[f32; 2]> = Vec::new();
+
+        for (index, w) in points.windows(2).enumerate() {
+            let delta = [ w[1][0] - w[0][0], w[1][1] - w[0][1] ];
+            let length = (delta[0].powi(2) + delta[1].powi(2)).sqrt();
+
+            if length < MOVE_EPSILON {
+                continue;
+            }
+
+            let dir = [ delta[0] / length, delta[1] / length ];
+
+            blocks.push(Block {
+                from: w[0],
+                to: w[1],
+                end: index + 1,
+                length,
+                accel: direction_limit(dir, config.max_accel),
+                entry_speed: 0.0,
+                cruise_speed: speed.min(direction_limit(dir, config.max_speed)),
+                exit_speed: 0.0
+            });
+            dirs.push(dir);
+        }
+
+        // Maximum speed at the start of every block, limited by the corner and the cruise speeds of both blocks
+        let junctions : Vec<f32> = (0 .. blocks.len()).map(|i| {
+            if i == 0 {
+                0.0
+            } else {
+                let accel = blocks[i - 1].accel.min(blocks[i].accel);
+                junction_speed(dirs[i - 1], dirs[i], accel, config.junction_deviation)
+                    .min(blocks[i - 1].cruise_speed).min(blocks[i].cruise_speed)
+            }
+        }).collect();
+
+        let mut entry = 0.0;
+
+        for i in 0 .. blocks.len() {
+            let window_end = (i + config.lookahead).min(blocks.len());
+
+            // Backward pass: highest speeds that still allow stopping at the end of the window
+            let mut exit : f32 = 0.0;
+
+            for j in ((i + 1) .. window_end).rev() {
+                exit = junctions[j].min((exit.powi(2) + 2.0 * blocks[j].accel * blocks[j].length).sqrt());
+            }
+
+            // Forward pass: the exit speed has to be reachable from the entry speed
+            let block = &mut blocks[i];
+            block.entry_speed = entry;
+            block.exit_speed = exit.min((entry.powi(2) + 2.0 * block.accel * block.length).sqrt());
+
+            entry = block.exit_speed;
+        }
+
+        blocks
+    }
+
+    /// Drives along a planned block with the step generator, updating the positions of the X- and Y-axis of the robot
+    /// 
+    /// The block has been planned with the override factor `planned_override`. If the override is lowered while driving, the speed
+    /// is reduced right away, raising it only takes effect with the next planned moves.
+    pub async fn move_block(rob : &mut DrakeRobot, stepper : &mut XyStepper, block : &Block, speed_override : &SpeedOverride, planned_override : f32) 
+    -> Result<(), syact::Error> {
+        let gammas = rob.gammas();
+        let from = [ gammas[0].0, gammas[1].0 ];
+
+        // Stepping blocks the thread until the end of the block
+        let reached = tokio::task::block_in_place(|| stepper.run_block(block, from, speed_override, planned_override));
+
+        let DrakeComponents { x, y, .. } = rob.comps_mut();
+        x.set_gamma(Gamma(reached[0]));
+        y.set_gamma(Gamma(reached[1]));
+
+        Ok(())
+    }
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG : PlannerConfig = PlannerConfig {
+        max_speed: [ 100.0, 100.0 ],
+        max_accel: [ 1000.0, 1000.0 ],
+        junction_deviation: 0.05,
+        lookahead: 16
+    };
+
+    #[test]
+    fn junction_speeds() {
+        // Straight continuation (0° corner) does not limit the speed, a reversal requires a stop
+        assert_eq!(junction_speed([ 1.0, 0.0 ], [ 1.0, 0.0 ], 1000.0, 0.05), f32::INFINITY);
+        assert_eq!(junction_speed([ 1.0, 0.0 ], [ -1.0, 0.0 ], 1000.0, 0.05), 0.0);
+
+        // 90° corner: sin(45°) / (1 - sin(45°)) = 1 + sqrt(2)
+        let speed = junction_speed([ 1.0, 0.0 ], [ 0.0, 1.0 ], 1000.0, 0.05);
+        assert!((speed - (1000.0 * 0.05 * (1.0 + 2.0f32.sqrt())).sqrt()).abs() < 1e-3, "{}", speed);
+
+        // Sharper corners are passed slower
+        assert!(junction_speed([ 1.0, 0.0 ], [ -core::f32::consts::FRAC_1_SQRT_2, core::f32::consts::FRAC_1_SQRT_2 ], 1000.0, 0.05) < speed);
+    }
+
+    #[test]
+    fn straight_polyline() {
+        let blocks = plan_polyline(&[ [ 0.0, 0.0 ], [ 50.0, 0.0 ], [ 100.0, 0.0 ] ], 50.0, &CONFIG);
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].entry_speed, 0.0);
+        // The pen keeps its full speed through the 0° corner
+        assert_eq!(blocks[0].exit_speed, 50.0);
+        assert_eq!(blocks[1].entry_speed, 50.0);
+        assert_eq!(blocks[1].exit_speed, 0.0);
+        assert_eq!([ blocks[0].end, blocks[1].end ], [ 1, 2 ]);
+    }
+
+    #[test]
+    fn right_angle_polyline() {
+        let points = [ [ 0.0, 0.0 ], [ 50.0, 0.0 ], [ 50.0, 50.0 ] ];
+        let blocks = plan_polyline(&points, 50.0, &CONFIG);
+        let corner = junction_speed([ 1.0, 0.0 ], [ 0.0, 1.0 ], 1000.0, 0.05);
+
+        assert!(corner < 50.0);
+        assert!((blocks[0].exit_speed - corner).abs() < 1e-3, "{:?}", blocks);
+        assert_eq!(blocks[1].entry_speed, blocks[0].exit_speed);
+        assert_eq!(blocks[1].exit_speed, 0.0);
+    }
+
+    #[test]
+    fn skips_zero_length_segments() {
+        let blocks = plan_polyline(&[ [ 0.0, 0.0 ], [ 0.0, 0.0 ], [ 10.0, 0.0 ] ], 50.0, &CONFIG);
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].end, 2);
+    }
+}
@@ -0,0 +1,154 @@
+use core::time::Duration;
+
+use serde::{Serialize, Deserialize};
+use syact::prelude::*;
+use sybot::prelude::*;
+
+use crate::DrakeRobot;
+use crate::config::DrakeConfig;
+use crate::servo_table::ServoTable;
+
+/// Device moving the pen up and down
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PenLiftBackend {
+    /// The pen is moved by the Z-axis of the robot
+    ZAxis {
+        /// Absolute Z-position of the pen while drawing (mm)
+        draw_height : f32,
+        /// Absolute Z-position of the pen while traveling between strokes (mm)
+        travel_height : f32
+    },
+    /// The pen is moved by a hobby servo on one of the free channels of the servo table
+    Servo {
+        /// Channel of the PCA9685 the servo is connected to (8 - 15)
+        channel : u8,
+        /// Servo signal with the pen lowered (ticks out of 4096)
+        signal_down : u16,
+        /// Servo signal with the pen lifted (ticks out of 4096)
+        signal_up : u16,
+        /// Time the servo takes to move between both positions (s)
+        move_time : f32
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PenLiftConfig {
+    pub backend : PenLiftBackend,
+    /// Time to wait after lowering the pen, so the ink can start flowing (s)
+    #[serde(default)]
+    pub dwell_time : f32
+}
+
+impl PenLiftConfig {
+    /// Z-axis lift moving between the drawing origin and the origin lifted by `z_lift`, used if the config has no pen lift
+    pub fn from_z_lift(config : &DrakeConfig) -> Self {
+        Self {
+            backend: PenLiftBackend::ZAxis {
+                draw_height: config.drawing_origin[2].0,
+                travel_height: config.drawing_origin[2].0 + config.z_lift.0
+            },
+            dwell_time: 0.0
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PenState {
+    /// The pen has not been moved by the lift yet, or the lift has been moved by something else (e.g. homing)
+    #[default]
+    Unknown,
+    Up,
+    Down
+}
+
+/// Lifts and lowers the pen, always moving to absolute positions so interrupted moves do not add up
+pub struct PenLift {
+    pub config : PenLiftConfig,
+    state : PenState
+}
+
+impl PenLift {
+    pub fn new(config : PenLiftConfig) -> Self {
+        Self {
+            config,
+            state: PenState::Unknown
+        }
+    }
+
+    pub fn from_config(config : &DrakeConfig) -> Self {
+        Self::new(config.pen_lift.clone().unwrap_or_else(|| PenLiftConfig::from_z_lift(config)))
+    }
+
+    pub fn state(&self) -> PenState {
+        self.state
+    }
+
+    pub fn is_down(&self) -> bool {
+        self.state == PenState::Down
+    }
+
+    /// Absolute Z-position of the pen while traveling, if the pen is moved by the Z-axis
+    pub fn travel_height(&self) -> Option<f32> {
+        match self.config.backend {
+            PenLiftBackend::ZAxis { travel_height, .. } => Some(travel_height),
+            PenLiftBackend::Servo { .. } => None
+        }
+    }
+
+    /// Absolute Z-position of the pen while drawing, if the pen is moved by the Z-axis
+    pub fn draw_height(&self) -> Option<f32> {
+        match self.config.backend {
+            PenLiftBackend::ZAxis { draw_height, .. } => Some(draw_height),
+            PenLiftBackend::Servo { .. } => None
+        }
+    }
+
+    /// Forgets the current state, so the next `up()` or `down()` moves the lift in any case
+    pub fn invalidate(&mut self) {
+        self.state = PenState::Unknown;
+    }
+
+    async fn move_to(&mut self, rob : &mut DrakeRobot, servo_table : &mut ServoTable, down : bool, factor : Factor) -> Result<(), syact::Error> {
+        // The state is unknown until the move has finished
+        self.state = PenState::Unknown;
+
+        match &self.config.backend {
+            PenLiftBackend::ZAxis { draw_height, travel_height } => {
+                let height = if down { *draw_height } else { *travel_height };
+                rob.comps_mut().z.drive_abs(Gamma(height), factor).await?;
+            },
+            PenLiftBackend::Servo { channel, signal_down, signal_up, move_time } => {
+                servo_table.set_aux_signal(*channel, if down { *signal_down } else { *signal_up })?;
+                tokio::time::sleep(Duration::from_secs_f32(*move_time)).await;
+            }
+        }
+
+        self.state = if down { PenState::Down } else { PenState::Up };
+        Ok(())
+    }
+
+    /// Lifts the pen to the travel position, the factor only applies to the Z-axis backend
+    pub async fn up(&mut self, rob : &mut DrakeRobot, servo_table : &mut ServoTable, factor : Factor) -> Result<(), syact::Error> {
+        if self.state == PenState::Up {
+            return Ok(());
+        }
+
+        self.move_to(rob, servo_table, false, factor).await
+    }
+
+    /// Lowers the pen to the drawing position and waits for the dwell time, the factor only applies to the Z-axis backend
+    pub async fn down(&mut self, rob : &mut DrakeRobot, servo_table : &mut ServoTable, factor : Factor) -> Result<(), syact::Error> {
+        if self.state == PenState::Down {
+            return Ok(());
+        }
+
+        self.move_to(rob, servo_table, true, factor).await?;
+
+        if self.config.dwell_time > 0.0 {
+            tokio::time::sleep(Duration::from_secs_f32(self.config.dwell_time)).await;
+        }
+
+        Ok(())
+    }
+}
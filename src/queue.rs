@@ -0,0 +1,81 @@
+use core::fmt::Display;
+use std::path::{Path, PathBuf};
+
+/// Name of the directory inside the queue that drawn jobs are moved into
+pub const DONE_DIR : &str = "done";
+/// Name of the directory inside the queue that jobs which cannot be drawn are moved into
+pub const FAILED_DIR : &str = "failed";
+
+/// Extensions of the files that are picked up as jobs, see `Drawing::load()`
+pub const JOB_EXTENSIONS : [&str; 8] = [ "json", "svg", "hpgl", "plt", "hpg", "gcode", "gc", "nc" ];
+
+// Errors
+    #[derive(Clone, Debug)]
+    pub enum QueueError {
+        /// There are no jobs left in the queue
+        Empty
+    }
+
+    impl Display for QueueError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::Empty => f.write_str("Empty: There are no jobs in the queue!")
+            }
+        }
+    }
+
+    impl std::error::Error for QueueError { }
+//
+
+/// A directory of drawing files, drawn one after another in the order of their names
+pub struct JobQueue {
+    pub dir : PathBuf
+}
+
+impl JobQueue {
+    pub fn new<P : AsRef<Path>>(dir : P) -> Self {
+        Self { dir: dir.as_ref().to_path_buf() }
+    }
+
+    /// All queued jobs, sorted by name
+    pub fn jobs(&self) -> Result<Vec<PathBuf>, syact::Error> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut jobs : Vec<PathBuf> = std::fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| JOB_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+            ).collect();
+
+        jobs.sort();
+        Ok(jobs)
+    }
+
+    /// The job to draw next
+    pub fn next(&self) -> Result<PathBuf, syact::Error> {
+        Ok(self.jobs()?.into_iter().next().ok_or(QueueError::Empty)?)
+    }
+
+    fn move_into(&self, job : &Path, dir_name : &str) -> Result<PathBuf, syact::Error> {
+        let dir = self.dir.join(dir_name);
+        std::fs::create_dir_all(&dir)?;
+
+        let target = dir.join(job.file_name().ok_or("The job has no file name!")?);
+        std::fs::rename(job, &target)?;
+
+        Ok(target)
+    }
+
+    /// Moves a drawn job into the `DONE_DIR` of the queue, so it is not drawn again
+    pub fn complete(&self, job : &Path) -> Result<PathBuf, syact::Error> {
+        self.move_into(job, DONE_DIR)
+    }
+
+    /// Moves a job that cannot be drawn into the `FAILED_DIR` of the queue, so it does not block the jobs after it
+    pub fn fail(&self, job : &Path) -> Result<PathBuf, syact::Error> {
+        self.move_into(job, FAILED_DIR)
+    }
+}
@@ -1 +1,332 @@
-// use crate::ServoTable;
\ No newline at end of file
+use indicatif::ProgressBar;
+use syact::prelude::*;
+use sybot::prelude::*;
+
+use crate::{DrakeRobot, DrakeStation};
+use crate::drawing::{dist, Drawing};
+use crate::drawing::gcode::{GCodeOp, GCodeProgram};
+use crate::drawing::transform::DrawingTransform;
+use crate::halt::HaltError;
+use crate::job::PauseSignal;
+use crate::journal::{Journal, CHECKPOINT_LINES};
+use crate::motion::SpeedOverride;
+use crate::servo_table::{ServoTable, SERVO_STATE_CLOSED, SERVO_STATE_OPEN, SERVO_STATE_STANDBY};
+use crate::user_terminal::LedPattern;
+
+/// Interval in which a paused job checks whether it has been resumed
+pub const PAUSE_POLL_INTERVAL : core::time::Duration = core::time::Duration::from_millis(50);
+
+/// Homes the robot, closes the servo table and moves the lifted pen above the drawing origin
+pub async fn start_drawing(stat : &mut DrakeStation, rob : &mut DrakeRobot) -> Result<(), syact::Error> {
+    stat.limits.check_xy([ stat.drawing_origin[0].0, stat.drawing_origin[1].0 ])?;
+
+    stat.home(rob).await?;
+
+    stat.servo_table.set_all_closed()?;
+    stat.lift_pen(rob).await?;
+
+    let z = stat.pen_lift.travel_height().map(Phi).unwrap_or(stat.drawing_origin[2]);
+    rob.move_abs_j([ stat.drawing_origin[0], stat.drawing_origin[1], z ], Factor::HALF).await?;
+
+    log::info!("> Moving to drawing position done!");
+
+    Ok(())
+}
+
+/// Refuses drawings with segments outside of the soft limits or the paper, logging every offending segment
+pub fn check_drawing(stat : &DrakeStation, drawing : &Drawing, transform : &DrawingTransform) -> Result<(), syact::Error> {
+    if let Err(err) = stat.check_drawing(drawing, transform) {
+        log::error!("> The drawing exceeds the limits of the station!");
+
+        for line in err.to_string().lines() {
+            log::error!("| {}", line);
+        }
+
+        return Err(err.into());
+    }
+
+    Ok(())
+}
+
+/// Shows whether the job is running (heartbeat on the start LED) or paused (start LED blinking for resume, halt LED on)
+pub fn set_job_leds(stat : &mut DrakeStation, running : bool) {
+    if running {
+        stat.user_terminal.set_start_pattern(LedPattern::Heartbeat);
+        stat.user_terminal.set_halt_led(false);
+    } else {
+        stat.user_terminal.set_start_pattern(LedPattern::SlowBlink);
+        stat.user_terminal.set_halt_led(true);
+    }
+}
+
+/// Parks the head until the job is resumed, then lowers the pen again where it has been lifted
+pub async fn pause_job(stat : &mut DrakeStation, rob : &mut DrakeRobot) -> Result<(), syact::Error> {
+    let [ x, y ] = stat.pen_pos(rob);
+
+    log::info!("> Job paused at [ {}, {} ], press start to resume!", x, y);
+    set_job_leds(stat, false);
+
+    stat.park(rob).await?;
+
+    while stat.pause.is_paused() {
+        tokio::time::sleep(PAUSE_POLL_INTERVAL).await;
+
+        // The halt button still works while paused
+        stat.handle_interrupts(rob, Ok(())).await?;
+    }
+
+    log::info!("> Job resumed!");
+    set_job_leds(stat, true);
+
+    stat.reposition_pen(rob, [ Phi(x), Phi(y) ]).await
+}
+
+/// Draws the polyline like `DrakeStation::draw_polyline()`, pausing the job at the end of a segment if requested
+/// 
+/// `progress` is called with the amount of points of the polyline reached after every segment.
+pub async fn draw_pausable(stat : &mut DrakeStation, rob : &mut DrakeRobot, points : &[[Phi; 2]], speed : f32, 
+    progress : &mut dyn FnMut(usize) -> Result<(), syact::Error>) -> Result<(), syact::Error> {
+    let pause = stat.pause.clone();
+    let mut remaining = points;
+
+    loop {
+        let offset = points.len() - remaining.len();
+        let reached = stat.draw_polyline_until(rob, remaining, speed, &|| pause.is_paused(), &mut |count| progress(offset + count)).await?;
+        remaining = &remaining[reached..];
+
+        if pause.is_paused() {
+            pause_job(stat, rob).await?;
+        }
+
+        if remaining.is_empty() {
+            return Ok(());
+        }
+    }
+}
+
+/// Draws all strokes of the drawing, lifting the pen only between strokes that do not connect
+/// 
+/// Lines are drawn with the drawing speed of the station. The job can be paused at the end of every segment.
+/// If a journal is given, the progress is written into it every `CHECKPOINT_LINES` lines and at the end of every stroke.
+pub async fn draw_drawing(stat : &mut DrakeStation, rob : &mut DrakeRobot, drawing : &Drawing, transform : &DrawingTransform, mut journal : Option<&mut Journal>) 
+-> Result<(), syact::Error> {
+    let pb = ProgressBar::new(drawing.line_count() as u64);
+
+    set_job_leds(stat, true);
+
+    let mut last_point : Option<[Phi; 2]> = None;
+
+    for stroke in &drawing.strokes {
+        let Some(&start) = stroke.first() else {
+            continue;
+        };
+
+        let p1 = transform.convert_point(start);
+
+        if last_point != Some(p1) {
+            stat.reposition_pen(rob, p1).await?;
+        }
+
+        let points : Vec<[Phi; 2]> = stroke[1..].iter().map(|&point| transform.convert_point(point)).collect();
+
+        // Lines of the stroke already shown in the progress bar and recorded in the journal
+        let mut reported = 0;
+        let mut recorded = 0;
+
+        draw_pausable(stat, rob, &points, stat.speeds.draw, &mut |reached| {
+            pb.inc((reached - reported) as u64);
+            reported = reached;
+
+            if let Some(journal) = journal.as_deref_mut() {
+                if (reached - recorded >= CHECKPOINT_LINES) || (reached == points.len()) {
+                    let distance = stroke[recorded ..= reached].windows(2).map(|w| dist(transform.apply(w[0]), transform.apply(w[1]))).sum();
+                    journal.checkpoint(reached - recorded, distance)?;
+                    recorded = reached;
+                }
+            }
+
+            Ok(())
+        }).await?;
+
+        last_point = points.last().copied().or(Some(p1));
+    }
+
+    pb.finish_with_message("done");
+
+    stat.lift_pen(rob).await?;
+    stat.user_terminal.set_start_led(false);
+
+    if let Some(journal) = journal {
+        journal.finish()?;
+    }
+
+    Ok(())
+}
+
+/// Executes a G-code program, with its coordinates mapped by the given transform
+///
+/// Travel moves are only executed once the pen is lowered again, so multiple travel moves in a row result in a single pen lift.
+/// Drawing moves without a feed rate are drawn with the drawing speed of the station.
+pub async fn run_gcode(stat : &mut DrakeStation, rob : &mut DrakeRobot, program : &GCodeProgram, transform : &DrawingTransform) -> Result<(), syact::Error> {
+    let speed_default = stat.speeds.draw;
+
+    set_job_leds(stat, true);
+
+    let mut pos = [ 0.0, 0.0 ];
+    let mut pen_pos : Option<[f32; 2]> = None;
+
+    // Consecutive drawing moves with the same feed rate are planned together
+    let mut polyline : Vec<[Phi; 2]> = Vec::new();
+    let mut polyline_speed = speed_default;
+
+    for op in &program.ops {
+        if let GCodeOp::Draw { to, feed } = op {
+            let speed = feed.unwrap_or(speed_default);
+
+            if (pen_pos != Some(pos)) || (speed != polyline_speed) {
+                draw_pausable(stat, rob, &polyline, polyline_speed, &mut |_| Ok(())).await?;
+                polyline.clear();
+                polyline_speed = speed;
+            }
+
+            if pen_pos != Some(pos) {
+                stat.reposition_pen(rob, transform.convert_point(pos)).await?;
+            }
+
+            polyline.push(transform.convert_point(*to));
+
+            pos = *to;
+            pen_pos = Some(pos);
+            continue;
+        }
+
+        draw_pausable(stat, rob, &polyline, polyline_speed, &mut |_| Ok(())).await?;
+        polyline.clear();
+
+        match op {
+            GCodeOp::Travel(to) => {
+                pos = *to;
+            },
+            GCodeOp::Home => {
+                log::info!("| > Homing requested by program ... ");
+                start_drawing(stat, rob).await?;
+
+                // The program continues from the drawing origin
+                pos = [ 0.0, 0.0 ];
+                pen_pos = None;
+            },
+            GCodeOp::Dwell(duration) => {
+                tokio::time::sleep(*duration).await;
+            },
+            GCodeOp::Draw { .. } => { }
+        }
+    }
+
+    draw_pausable(stat, rob, &polyline, polyline_speed, &mut |_| Ok(())).await?;
+    stat.lift_pen(rob).await?;
+    stat.user_terminal.set_start_led(false);
+
+    Ok(())
+}
+
+/// Logs the state the robot has been left in if the job has been halted, passing the `HaltError` on so the job ends with an error
+pub fn handle_halt(result : Result<(), syact::Error>) -> Result<(), syact::Error> {
+    if let Some(HaltError::Halted(report)) = result.as_ref().err().and_then(|err| err.downcast_ref::<HaltError>()) {
+        log::warn!("> Job halted! Robot state: {}", report);
+    }
+
+    result
+}
+
+/// Adjusts the speed override from the console while a job is running
+/// 
+/// Every line read is either `+` or `-` (changing the override by 10%), a new percentage or `pause` / `resume`.
+pub fn spawn_speed_console(speed_override : SpeedOverride, pause : PauseSignal) {
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+
+            let percent = match line.trim() {
+                "pause" => {
+                    pause.pause();
+                    log::info!("| > Pausing after the next segment ... ");
+                    continue;
+                },
+                "resume" => {
+                    pause.resume();
+                    continue;
+                },
+                "+" => speed_override.adjust(10),
+                "-" => speed_override.adjust(-10),
+                value => match value.trim_end_matches('%').parse::<u32>() {
+                    Ok(percent) => speed_override.set_percent(percent),
+                    Err(_) => {
+                        log::warn!("Invalid speed override '{}'! Use '+', '-', a percentage, 'pause' or 'resume'", value);
+                        continue;
+                    }
+                }
+            };
+
+            log::info!("| > Speed override: {}%", percent);
+        }
+    });
+}
+
+/// Jogs the servos of the table one after another from the console to calibrate them
+/// 
+/// Every line read is one of
+/// - `+` / `-` / `++` / `--`: shifting the offset of the servo by 5 or 20 ticks
+/// - `min <TICKS>` / `max <TICKS>`: setting the signal range of the servo
+/// - `inv`: toggling the inversion of the servo
+/// - `open` / `closed` / `standby`: moving the servo into the given state, all changes are shown in the current state
+/// - `next` or an empty line: continuing with the next servo
+/// - `save` / `quit`: stopping, with or without saving the calibration
+/// 
+/// Returns whether the calibration should be saved.
+pub fn calibrate_servos(table : &mut ServoTable, ids : &[u8]) -> Result<bool, syact::Error> {
+    log::info!("> Commands: '+', '-', '++', '--', 'min <TICKS>', 'max <TICKS>', 'inv', 'open', 'closed', 'standby', 'next', 'save', 'quit'");
+
+    let mut lines = std::io::stdin().lines();
+
+    for &id in ids {
+        let mut state = SERVO_STATE_OPEN;
+
+        loop {
+            table.set_servo_angle(id, state)?;
+
+            let calibration = table.calibration[id as usize];
+            log::info!("| > Servo {}: {:?}, signal: {}", id, calibration, table.signals[id as usize]);
+
+            let Some(line) = lines.next() else {
+                return Ok(false);
+            };
+
+            let line = line?;
+            let mut args = line.split_whitespace();
+            let calibration = &mut table.calibration[id as usize];
+
+            match (args.next().unwrap_or("next"), args.next().map(str::parse::<u16>)) {
+                ("+", _) => calibration.offset += 5,
+                ("-", _) => calibration.offset -= 5,
+                ("++", _) => calibration.offset += 20,
+                ("--", _) => calibration.offset -= 20,
+                ("min", Some(Ok(signal))) => calibration.min_signal = signal,
+                ("max", Some(Ok(signal))) => calibration.max_signal = signal,
+                ("inv", _) => calibration.inverted = !calibration.inverted,
+                ("open", _) => state = SERVO_STATE_OPEN,
+                ("closed", _) => state = SERVO_STATE_CLOSED,
+                ("standby", _) => state = SERVO_STATE_STANDBY,
+                ("next", _) => break,
+                ("save", _) => return Ok(true),
+                ("quit", _) => return Ok(false),
+                _ => log::warn!("Invalid command '{}'!", line.trim())
+            }
+        }
+
+        table.set_servo_open(id)?;
+    }
+
+    Ok(true)
+}
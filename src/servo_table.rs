@@ -1,11 +1,17 @@
 use core::fmt::Display;
 use core::time::Duration;
+use std::collections::HashMap;
+use std::time::Instant;
 
+use embedded_hal::i2c::{ErrorKind, I2c, NoAcknowledgeSource};
 use pwm_pca9685::{Address, Channel, Pca9685};
-use rppal::i2c::I2c;
+use serde::{Serialize, Deserialize};
 use syact::Setup;
 use syunit::*;
 
+use crate::config::DrakeConfig;
+use crate::hal::{Hal, SharedI2c, PCA9685_ADDRESS};
+
 
 // Servo signals
     /// The amount of ticks the servo PWM will stay on for it to be off (out of 4096)
@@ -13,39 +19,226 @@ use syunit::*;
     /// The amount of ticks the servo PWM will stay on for it to be off (out of 4096)
     pub const SERVO_SIG_MAX : u16 = 512;
 
+    /// Range of signals that may be written to a servo at all, wider than the default range for servos with longer pulses
+    pub const SERVO_SIG_LIMITS : [u16; 2] = [ 50, 650 ];
+
     /// Minimum Servo angle
     pub const SERVO_ANG_MIN : Gamma = Gamma::ZERO;
     /// Maximum Servo angle
     pub const SERVO_ANG_MAX : Gamma = Gamma(core::f32::consts::PI);
 
     /// Returns the required amount of ticks for the servo pwm signal for the servo to match the given angle
-    pub fn signal_for_angle(angle : Gamma) -> Option<u16> {
+    pub fn signal_for_angle(angle : Gamma, calibration : &ServoCalibration) -> Option<u16> {
         if angle < SERVO_ANG_MIN {
             None    // Angle out of range (smaller)
         } else if angle > SERVO_ANG_MAX {
             None    // Angle out of range (bigger)
         } else {
-            Some(
-                SERVO_SIG_MAX   // TODO: FIX
-                // (((angle - SERVO_ANG_MIN) / (SERVO_ANG_MAX - SERVO_ANG_MIN)).into() * ((SERVO_SIG_MAX - SERVO_SIG_MIN) as f32)) as u16 + SERVO_SIG_MIN
-            )
+            let ratio = (angle - SERVO_ANG_MIN).0 / (SERVO_ANG_MAX - SERVO_ANG_MIN).0;
+            let range = calibration.max_signal.saturating_sub(calibration.min_signal) as f32;
+
+            Some(calibration.apply(calibration.min_signal + (ratio * range).round() as u16))
         }
     }
 // 
 
 // Configuration
-    /// Whether the servo with the given ID should be inverted or not
-    pub const SERVO_INV : [bool; 8] = [ false, false, true, false, true, true, false, true ];
+    /// Signal range, orientation and offset of a single servo
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct ServoCalibration {
+        /// Signal at the minimum angle (ticks out of 4096)
+        pub min_signal : u16,
+        /// Signal at the maximum angle (ticks out of 4096)
+        pub max_signal : u16,
+        /// Mirrors the signals inside the range, for servos mounted the other way around
+        #[serde(default)]
+        pub inverted : bool,
+        /// Shifts the signal (ticks), so the orientation of the servos can be equalized, applied before the inversion
+        #[serde(default)]
+        pub offset : i16
+    }
+
+    impl ServoCalibration {
+        pub const fn new(inverted : bool, offset : i16) -> Self {
+            Self {
+                min_signal: SERVO_SIG_MIN,
+                max_signal: SERVO_SIG_MAX,
+                inverted,
+                offset
+            }
+        }
+
+        /// Applies the offset and inversion to a signal inside of the range
+        pub fn apply(&self, signal : u16) -> u16 {
+            let [ min, max ] = self.limits();
+            let signal = signal.saturating_add_signed(self.offset).clamp(min, max);
+
+            if self.inverted {
+                max - signal + min
+            } else {
+                signal
+            }
+        }
+
+        /// The signal range, limited to `SERVO_SIG_LIMITS`
+        pub fn limits(&self) -> [u16; 2] {
+            let min = self.min_signal.clamp(SERVO_SIG_LIMITS[0], SERVO_SIG_LIMITS[1]);
+            [ min, self.max_signal.clamp(min, SERVO_SIG_LIMITS[1]) ]
+        }
+    }
+
+    impl Default for ServoCalibration {
+        fn default() -> Self {
+            Self::new(false, 0)
+        }
+    }
 
-    /// Shifts the servo signal, so the servos orientation can be equalized
-    pub const SERVO_SHIFT : [i16; 8] = [ 170, 150, 0, 190, 10, 30, 160, 40 ];
+    /// Calibration of the servos of the table as built, used if the config has none
+    pub const DEFAULT_CALIBRATION : [ServoCalibration; 8] = [
+        ServoCalibration::new(false, 170), ServoCalibration::new(false, 150), 
+        ServoCalibration::new(true, 0), ServoCalibration::new(false, 190), 
+        ServoCalibration::new(true, 10), ServoCalibration::new(true, 30), 
+        ServoCalibration::new(false, 160), ServoCalibration::new(true, 40)
+    ];
 
     /// Servo position in the "closed" state
-    pub const SERVO_STATE_CLOSED : u16 = SERVO_SIG_MAX;
+    pub const SERVO_STATE_CLOSED : Gamma = SERVO_ANG_MAX;
     /// Servo position in the "open" state
-    pub const SERVO_STATE_OPEN : u16 = SERVO_SIG_MIN;
-    /// Servo position in the "standby" state
-    pub const SERVO_STATE_STANDBY : u16 = SERVO_SIG_MIN + 50;
+    pub const SERVO_STATE_OPEN : Gamma = SERVO_ANG_MIN;
+    /// Servo position in the "standby" state, slightly closed
+    pub const SERVO_STATE_STANDBY : Gamma = Gamma(core::f32::consts::PI * 0.12);
+// 
+
+// Poses & Transitions
+    /// Interval in which the servos are updated during a transition, matching the period of the servo signal
+    pub const SERVO_FRAME_TIME : Duration = Duration::from_millis(20);
+
+    /// Delay between the servos of the roll sequence at speed 1.0 (s)
+    pub const ROLL_STAGGER : f32 = 0.12;
+
+    /// Angles of all servos of the table
+    pub type ServoPose = [Gamma; 8];
+
+    /// Poses that are always available, the config can add further ones or override them
+    pub fn default_poses() -> HashMap<String, ServoPose> {
+        HashMap::from([
+            (String::from("open"), [ SERVO_STATE_OPEN; 8 ]),
+            (String::from("closed"), [ SERVO_STATE_CLOSED; 8 ]),
+            (String::from("standby"), [ SERVO_STATE_STANDBY; 8 ])
+        ])
+    }
+
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum Easing {
+        Linear,
+        EaseIn,
+        EaseOut,
+        #[default]
+        EaseInOut
+    }
+
+    impl Easing {
+        /// Maps the progress of a transition (0.0 - 1.0) to the progress of the servo
+        pub fn apply(&self, t : f32) -> f32 {
+            match self {
+                Self::Linear => t,
+                Self::EaseIn => t * t,
+                Self::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+                Self::EaseInOut => t * t * (3.0 - 2.0 * t)
+            }
+        }
+    }
+
+    /// How servos move from one angle to another
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+    pub struct ServoTransition {
+        /// Duration of the move of a single servo (s), moving instantly if zero
+        #[serde(default)]
+        pub duration : f32,
+        #[serde(default)]
+        pub easing : Easing
+    }
+
+    impl ServoTransition {
+        pub const INSTANT : Self = Self { duration: 0.0, easing: Easing::Linear };
+
+        /// Angle of a servo moving from `from` to `to` the given time after its transition started, and whether it has arrived
+        pub fn angle_at(&self, from : Gamma, to : Gamma, elapsed : Duration) -> (Gamma, bool) {
+            let t = if self.duration <= 0.0 {
+                1.0
+            } else {
+                (elapsed.as_secs_f32() / self.duration).min(1.0)
+            };
+
+            (Gamma(from.0 + (to.0 - from.0) * self.easing.apply(t)), t >= 1.0)
+        }
+    }
+
+    /// A step of a `ServoSequence`
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct SequenceStep {
+        /// Name of the pose moved to
+        pub pose : String,
+        /// Delay between the start of the transitions of consecutive servos (s), all servos move together if zero
+        #[serde(default)]
+        pub stagger : f32,
+        /// Transition of the step, defaults to the transition of the table
+        #[serde(default)]
+        pub transition : Option<ServoTransition>
+    }
+
+    /// Poses moved to one after another
+    pub type ServoSequence = Vec<SequenceStep>;
+// 
+
+// Board
+    /// Prescale of the PWM clock, resulting in a servo signal of about 60 Hz
+    pub const PRESCALE : u8 = 100;
+
+    /// Register holding the sleep bit of the PCA9685
+    pub const REG_MODE1 : u8 = 0x00;
+    /// Register holding the prescale of the PCA9685
+    pub const REG_PRE_SCALE : u8 = 0xFE;
+    /// Oscillator turned off, no signals are generated
+    pub const MODE1_SLEEP : u8 = 0x10;
+
+    /// Amount of attempts of an I2C transfer before it is treated as failed
+    pub const I2C_ATTEMPTS : usize = 3;
+    /// Delay between two attempts of an I2C transfer
+    pub const I2C_RETRY_DELAY : Duration = Duration::from_millis(5);
+
+    /// Repeats the given I2C transfer until it succeeds, at most `I2C_ATTEMPTS` times
+    /// 
+    /// Blocks the thread between the attempts, async code uses `with_retries_async()` instead.
+    pub fn with_retries<T, E>(mut transfer : impl FnMut() -> Result<T, E>) -> Result<T, E> {
+        let mut attempt = 1;
+
+        loop {
+            match transfer() {
+                Err(_) if attempt < I2C_ATTEMPTS => {
+                    attempt += 1;
+                    std::thread::sleep(I2C_RETRY_DELAY);
+                },
+                result => return result
+            }
+        }
+    }
+
+    /// Like `with_retries()`, but waits between the attempts without blocking the async runtime
+    pub async fn with_retries_async<T, E>(mut transfer : impl FnMut() -> Result<T, E>) -> Result<T, E> {
+        let mut attempt = 1;
+
+        loop {
+            match transfer() {
+                Err(_) if attempt < I2C_ATTEMPTS => {
+                    attempt += 1;
+                    tokio::time::sleep(I2C_RETRY_DELAY).await;
+                },
+                result => return result
+            }
+        }
+    }
 // 
 
 // Errors & Helpers
@@ -55,78 +248,205 @@ use syunit::*;
         Channel::C4, Channel::C5, Channel::C6, Channel::C7
     ];
 
+    /// Channels of the PCA9685 not used by the table, free for additional servos (e.g. a pen lift)
+    pub const AUX_CHANNEL_IDS : [Channel; 8] = [
+        Channel::C8, Channel::C9, Channel::C10, Channel::C11,
+        Channel::C12, Channel::C13, Channel::C14, Channel::C15
+    ];
+
     #[derive(Debug, Clone)]
     pub enum ServoTableError {
         BadId(u8),
-        AngleOutOfRange(u8, Gamma)
+        BadChannel(u8),
+        AngleOutOfRange(u8, Gamma),
+        UnknownPose(String),
+        UnknownSequence(String),
+        /// Communication with the PCA9685 failed, even after retrying
+        Board(ErrorKind),
+        /// The PCA9685 does not answer on its address
+        BoardMissing,
+        /// A register of the PCA9685 does not hold the expected value (register, expected, actual)
+        RegisterMismatch(u8, u8, u8)
     }
 
     impl Display for ServoTableError {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            match *self {
+            match self {
                 Self::BadId(id) => f.write_fmt(format_args!("BadId: The given servo-id '{id}' is invalid!")),
+                Self::BadChannel(channel) => f.write_fmt(format_args!("BadChannel: The channel '{channel}' is not a free channel (8 - 15)!")),
                 Self::AngleOutOfRange(id, ang) => 
-                    f.write_fmt(format_args!("AngleOutOfRange: The given angle '{ang}' for servo {id} is out of range!"))
+                    f.write_fmt(format_args!("AngleOutOfRange: The given angle '{ang}' for servo {id} is out of range!")),
+                Self::UnknownPose(name) => f.write_fmt(format_args!("UnknownPose: There is no servo pose named '{name}'!")),
+                Self::UnknownSequence(name) => f.write_fmt(format_args!("UnknownSequence: There is no servo sequence named '{name}'!")),
+                Self::Board(kind) => f.write_fmt(format_args!("Board: The communication with the PCA9685 failed! ({kind})")),
+                Self::BoardMissing => 
+                    f.write_fmt(format_args!("BoardMissing: The PCA9685 does not answer on address {PCA9685_ADDRESS:#04x}, check its wiring and power supply!")),
+                Self::RegisterMismatch(register, expected, actual) => 
+                    f.write_fmt(format_args!("RegisterMismatch: The register {register:#04x} of the PCA9685 holds {actual:#04x} instead of {expected:#04x}!"))
             }
         }
     }
 
     impl std::error::Error for ServoTableError { }
+
+    impl From<ErrorKind> for ServoTableError {
+        fn from(kind : ErrorKind) -> Self {
+            match kind {
+                ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address) => Self::BoardMissing,
+                kind => Self::Board(kind)
+            }
+        }
+    }
+
+    impl From<pwm_pca9685::Error<ErrorKind>> for ServoTableError {
+        fn from(err : pwm_pca9685::Error<ErrorKind>) -> Self {
+            match err {
+                pwm_pca9685::Error::I2C(kind) => kind.into(),
+                // Only caused by values outside of the 12 bit range, which are clamped before
+                pwm_pca9685::Error::InvalidInputData => Self::Board(ErrorKind::Other)
+            }
+        }
+    }
 // 
 
 pub struct ServoTable {
-    pub pwm : Pca9685<I2c>,
-    pub signals : [u16; 8]
+    pub pwm : Pca9685<SharedI2c>,
+    /// Bus of the PCA9685, shared with the driver, used to read back its registers
+    bus : SharedI2c,
+    pub calibration : [ServoCalibration; 8],
+    pub signals : [u16; 8],
+    /// Last angles set, `None` if unknown or the servo has been set to a raw signal
+    pub angles : [Option<Gamma>; 8],
+
+    pub poses : HashMap<String, ServoPose>,
+    pub sequences : HashMap<String, ServoSequence>,
+    /// Transition used when moving to a pose
+    pub transition : ServoTransition
 }
 
 impl ServoTable {
-    pub fn new(i2c : I2c) -> Result<Self, Box<dyn std::error::Error>> {
-        let pwm = Pca9685::new(i2c, Address::default())?;
+    pub fn new(hal : &Hal, calibration : [ServoCalibration; 8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let bus = SharedI2c::new(hal.i2c()?);
+        let pwm = Pca9685::new(bus.clone(), Address::default())?;
 
         Ok(Self {
             pwm,
-            signals: [0; 8]
+            bus,
+            calibration,
+            signals: [0; 8],
+            angles: [None; 8],
+
+            poses: default_poses(),
+            sequences: HashMap::new(),
+            transition: ServoTransition::default()
         })
     }
 
-    pub fn set_servo_signal(&mut self, id : u8, mut signal : u16) -> Result<(), ServoTableError> {
+    /// Creates the table with the calibration, poses, sequences and transition of the config
+    pub fn from_config(hal : &Hal, config : &DrakeConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut table = Self::new(hal, config.servos)?;
+
+        table.poses.extend(config.servo_poses.clone());
+        table.sequences = config.servo_sequences.clone();
+        table.transition = config.servo_transition;
+
+        Ok(table)
+    }
+
+    /// Checks the servo id and limits the signal to `SERVO_SIG_LIMITS`
+    fn checked_signal(id : u8, signal : u16) -> Result<u16, ServoTableError> {
         // Check if the servo id given is valid
         if id >= 8 {
             return Err(ServoTableError::BadId(id));
         }
 
-        // Shift signal
-        signal = signal.saturating_add_signed(SERVO_SHIFT[id as usize]).clamp(SERVO_SIG_MIN, SERVO_SIG_MAX);
+        Ok(signal.clamp(SERVO_SIG_LIMITS[0], SERVO_SIG_LIMITS[1]))
+    }
+
+    /// Signal moving the servo to the given angle, using its calibration
+    fn angle_signal(&self, id : u8, angle : Gamma) -> Result<u16, ServoTableError> {
+        let calibration = self.calibration.get(id as usize).ok_or(ServoTableError::BadId(id))?;
+        signal_for_angle(angle, calibration).ok_or(ServoTableError::AngleOutOfRange(id, angle))
+    }
 
-        // Optionally invert signal
-        if SERVO_INV[id as usize] {
-            signal = SERVO_SIG_MAX - signal + SERVO_SIG_MIN;
-        }
+    /// Writes the signal to the servo as it is, only limited to `SERVO_SIG_LIMITS`
+    pub fn set_servo_signal(&mut self, id : u8, signal : u16) -> Result<(), ServoTableError> {
+        let signal = Self::checked_signal(id, signal)?;
 
-        self.pwm.set_channel_on_off(CHANNEL_IDS[id as usize], 0, signal).unwrap();   // TODO: Add board error
+        with_retries(|| self.pwm.set_channel_on_off(CHANNEL_IDS[id as usize], 0, signal))?;
         self.signals[id as usize] = signal;
+        self.angles[id as usize] = None;
+
+        Ok(())
+    }
+
+    /// Sets the signal of a servo on one of the free channels (8 - 15), without any shift or inversion
+    pub fn set_aux_signal(&mut self, channel : u8, signal : u16) -> Result<(), ServoTableError> {
+        let channel_id = *channel.checked_sub(8)
+            .and_then(|index| AUX_CHANNEL_IDS.get(index as usize))
+            .ok_or(ServoTableError::BadChannel(channel))?;
+
+        with_retries(|| self.pwm.set_channel_on_off(channel_id, 0, signal.clamp(SERVO_SIG_MIN, SERVO_SIG_MAX)))?;
+
+        Ok(())
+    }
+
+    /// Reads a register of the PCA9685
+    pub fn read_register(&mut self, register : u8) -> Result<u8, ServoTableError> {
+        let mut buffer = [ 0 ];
+        with_retries(|| self.bus.write_read(PCA9685_ADDRESS, &[ register ], &mut buffer))?;
+        Ok(buffer[0])
+    }
+
+    /// Checks that the PCA9685 answers and holds the configuration written by `setup()`
+    pub fn self_test(&mut self) -> Result<(), ServoTableError> {
+        let mode1 = self.read_register(REG_MODE1)?;
+
+        if (mode1 & MODE1_SLEEP) != 0 {
+            return Err(ServoTableError::RegisterMismatch(REG_MODE1, mode1 & !MODE1_SLEEP, mode1));
+        }
+
+        let prescale = self.read_register(REG_PRE_SCALE)?;
+
+        if prescale != PRESCALE {
+            return Err(ServoTableError::RegisterMismatch(REG_PRE_SCALE, PRESCALE, prescale));
+        }
 
         Ok(())
     }
 
+    /// Moves the servo to the given angle, using its calibration
     pub fn set_servo_angle(&mut self, id : u8, angle : Gamma) -> Result<(), ServoTableError> {
-        // Get the signal for the given angle and write it to the servo
-        let signal = signal_for_angle(angle).ok_or(ServoTableError::AngleOutOfRange(id, angle))?;
+        let signal = self.angle_signal(id, angle)?;
         
-        self.set_servo_signal(id, signal)
+        self.set_servo_signal(id, signal)?;
+        self.angles[id as usize] = Some(angle);
+
+        Ok(())
+    }
+
+    /// Like `set_servo_angle()`, but retries failed transfers without blocking the async runtime
+    pub async fn set_servo_angle_async(&mut self, id : u8, angle : Gamma) -> Result<(), ServoTableError> {
+        let signal = Self::checked_signal(id, self.angle_signal(id, angle)?)?;
+
+        with_retries_async(|| self.pwm.set_channel_on_off(CHANNEL_IDS[id as usize], 0, signal)).await?;
+        self.signals[id as usize] = signal;
+        self.angles[id as usize] = Some(angle);
+
+        Ok(())
     }
 
     // Servo states
         pub fn set_servo_closed(&mut self, id : u8) -> Result<(), ServoTableError> {
-            self.set_servo_signal(id, SERVO_STATE_CLOSED)
+            self.set_servo_angle(id, SERVO_STATE_CLOSED)
         }
 
         pub fn set_servo_open(&mut self, id : u8) -> Result<(), ServoTableError> {
-            self.set_servo_signal(id, SERVO_STATE_OPEN)
+            self.set_servo_angle(id, SERVO_STATE_OPEN)
         }
 
         pub fn set_servo_standby(&mut self, id : u8) -> Result<(), ServoTableError> {
-            self.set_servo_signal(id, SERVO_STATE_STANDBY)
+            self.set_servo_angle(id, SERVO_STATE_STANDBY)
         }
     // 
 
@@ -155,29 +475,258 @@ impl ServoTable {
             Ok(())
         }
 
-        pub fn roll_servos(&mut self, speed : f32) -> Result<(), ServoTableError> {
-            self.set_all_open()?;
+        /// Closes and opens the servos one after another
+        pub async fn roll_servos(&mut self, speed : f32) -> Result<(), ServoTableError> {
+            self.move_to_angles(&[ SERVO_STATE_OPEN; 8 ], &ServoTransition::INSTANT, Duration::ZERO).await?;
+
+            let stagger = ROLL_STAGGER / speed;
+            let step = |pose : &str| SequenceStep { pose: String::from(pose), stagger, transition: Some(ServoTransition::INSTANT) };
+
+            self.play_sequence(&[ step("closed"), step("open") ]).await?;
+
+            // Give the last servo time to open
+            tokio::time::sleep(Duration::from_secs_f32(stagger)).await;
+
+            Ok(())
+        }
+    // 
 
-            for id in 0 .. 8 {
-                self.set_servo_closed(id)?;
-                std::thread::sleep(Duration::from_secs_f32(0.12 / speed));
+    // Poses
+        pub fn pose(&self, name : &str) -> Result<ServoPose, ServoTableError> {
+            self.poses.get(name).copied().ok_or_else(|| ServoTableError::UnknownPose(name.to_string()))
+        }
+
+        /// Moves all servos to the given angles, the transition of each servo starting `stagger` after the one of the servo before
+        /// 
+        /// Servos with unknown angles jump to their target when their transition starts.
+        pub async fn move_to_angles(&mut self, target : &ServoPose, transition : &ServoTransition, stagger : Duration) -> Result<(), ServoTableError> {
+            let from : ServoPose = core::array::from_fn(|index| self.angles[index].unwrap_or(target[index]));
+            let total = stagger * 7 + Duration::from_secs_f32(transition.duration.max(0.0));
+
+            let start = Instant::now();
+            let mut done = [ false; 8 ];
+
+            loop {
+                let elapsed = start.elapsed();
+
+                for id in 0 .. 8 {
+                    let begin = stagger * id as u32;
+
+                    if done[id] || (elapsed < begin) {
+                        continue;
+                    }
+
+                    let (angle, arrived) = transition.angle_at(from[id], target[id], elapsed - begin);
+                    self.set_servo_angle_async(id as u8, angle).await?;
+
+                    done[id] = arrived;
+                }
+
+                if elapsed >= total {
+                    return Ok(());
+                }
+
+                tokio::time::sleep(SERVO_FRAME_TIME).await;
             }
+        }
+
+        /// Moves all servos together to the pose with the given name, using the transition of the table
+        pub async fn move_to_pose(&mut self, name : &str) -> Result<(), ServoTableError> {
+            let pose = self.pose(name)?;
+            let transition = self.transition;
+
+            self.move_to_angles(&pose, &transition, Duration::ZERO).await
+        }
+
+        pub async fn play_sequence(&mut self, steps : &[SequenceStep]) -> Result<(), ServoTableError> {
+            for step in steps {
+                let pose = self.pose(&step.pose)?;
+                let transition = step.transition.unwrap_or(self.transition);
 
-            for id in 0 .. 8 {
-                self.set_servo_open(id)?;
-                std::thread::sleep(Duration::from_secs_f32(0.12 / speed));
+                self.move_to_angles(&pose, &transition, Duration::from_secs_f32(step.stagger.max(0.0))).await?;
             }
 
             Ok(())
         }
+
+        /// Plays the sequence with the given name from the config
+        pub async fn run_sequence(&mut self, name : &str) -> Result<(), ServoTableError> {
+            let steps = self.sequences.get(name).cloned().ok_or_else(|| ServoTableError::UnknownSequence(name.to_string()))?;
+            self.play_sequence(&steps).await
+        }
     // 
 }
 
 impl Setup for ServoTable { 
     fn setup(&mut self) -> Result<(), syact::Error> {
-        self.pwm.enable()?;
-        self.pwm.set_prescale(100)?;
+        with_retries(|| self.pwm.enable()).map_err(ServoTableError::from)?;
+        with_retries(|| self.pwm.set_prescale(PRESCALE)).map_err(ServoTableError::from)?;
+
+        self.self_test()?;
+        log::info!("| > PCA9685 self-test passed!");
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::hal::VirtualBoard;
+
+    fn table() -> (VirtualBoard, ServoTable) {
+        let board = VirtualBoard::new();
+        let table = ServoTable::new(&Hal::Virtual(board.clone()), [ ServoCalibration::default(); 8 ]).unwrap();
+        (board, table)
+    }
+
+    fn table_error(err : syact::Error) -> ServoTableError {
+        err.downcast_ref::<ServoTableError>().cloned().unwrap_or_else(|| panic!("{}", err))
+    }
+
+    #[test]
+    fn signals_for_angles() {
+        use core::f32::consts::{FRAC_PI_2, PI};
+
+        let signal = |angle : f32, calibration : ServoCalibration| signal_for_angle(Gamma(angle), &calibration);
+        let plain = ServoCalibration::default();
+
+        assert_eq!(signal(0.0, plain), Some(SERVO_SIG_MIN));
+        assert_eq!(signal(FRAC_PI_2, plain), Some(307));
+        assert_eq!(signal(PI, plain), Some(SERVO_SIG_MAX));
+
+        // Angles outside of 0 - 180°
+        assert_eq!(signal(-0.1, plain), None);
+        assert_eq!(signal(PI + 0.1, plain), None);
+
+        // Inverted servos are mirrored inside the range
+        let inverted = ServoCalibration::new(true, 0);
+        assert_eq!(signal(0.0, inverted), Some(SERVO_SIG_MAX));
+        assert_eq!(signal(FRAC_PI_2, inverted), Some(307));
+        assert_eq!(signal(PI, inverted), Some(SERVO_SIG_MIN));
+
+        // Offsets shift the signal inside the range, before the inversion
+        assert_eq!(signal(0.0, ServoCalibration::new(false, 20)), Some(122));
+        assert_eq!(signal(PI, ServoCalibration::new(false, 20)), Some(SERVO_SIG_MAX));
+        assert_eq!(signal(0.0, ServoCalibration::new(false, -20)), Some(SERVO_SIG_MIN));
+        assert_eq!(signal(0.0, ServoCalibration::new(true, 20)), Some(492));
+
+        // Custom ranges, limited to `SERVO_SIG_LIMITS`
+        let custom = ServoCalibration { min_signal: 150, max_signal: 450, inverted: false, offset: 0 };
+        assert_eq!(signal(FRAC_PI_2, custom), Some(300));
+
+        let wide = ServoCalibration { min_signal: 20, max_signal: 700, inverted: false, offset: 0 };
+        assert_eq!(wide.limits(), SERVO_SIG_LIMITS);
+        assert_eq!(signal(0.0, wide), Some(SERVO_SIG_LIMITS[0]));
+        assert_eq!(signal(PI, wide), Some(SERVO_SIG_LIMITS[1]));
+    }
+
+    #[test]
+    fn easing_curves() {
+        for easing in [ Easing::Linear, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut ] {
+            assert_eq!(easing.apply(0.0), 0.0, "{:?}", easing);
+            assert_eq!(easing.apply(1.0), 1.0, "{:?}", easing);
+        }
+
+        assert_eq!(Easing::Linear.apply(0.5), 0.5);
+        assert_eq!(Easing::EaseIn.apply(0.5), 0.25);
+        assert_eq!(Easing::EaseOut.apply(0.5), 0.75);
+        assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+
+        // Slow start, symmetric around the midpoint
+        assert_eq!(Easing::EaseInOut.apply(0.25), 0.15625);
+        assert_eq!(Easing::EaseInOut.apply(0.75), 0.84375);
+    }
+
+    #[test]
+    fn transition_angles() {
+        let ms = Duration::from_millis;
+        let linear = ServoTransition { duration: 0.2, easing: Easing::Linear };
+        let ease_in = ServoTransition { duration: 0.2, easing: Easing::EaseIn };
+
+        assert_eq!(linear.angle_at(Gamma(1.0), Gamma(2.0), ms(0)), (Gamma(1.0), false));
+        assert_eq!(linear.angle_at(Gamma(1.0), Gamma(2.0), ms(100)), (Gamma(1.5), false));
+        assert_eq!(linear.angle_at(Gamma(1.0), Gamma(2.0), ms(200)), (Gamma(2.0), true));
+        assert_eq!(linear.angle_at(Gamma(1.0), Gamma(2.0), ms(500)), (Gamma(2.0), true));
+
+        // Moving backwards with easing
+        assert_eq!(ease_in.angle_at(Gamma(2.0), Gamma(1.0), ms(100)), (Gamma(1.75), false));
+
+        // Instant transitions arrive right away
+        assert_eq!(ServoTransition::INSTANT.angle_at(Gamma(0.0), Gamma(3.0), ms(0)), (Gamma(3.0), true));
+    }
+
+    #[tokio::test]
+    async fn moves_to_poses() {
+        let (_board, mut table) = table();
+        table.setup().unwrap();
+        table.transition = ServoTransition { duration: 0.05, easing: Easing::EaseInOut };
+
+        // Servos with unknown angles jump to the pose
+        table.move_to_pose("standby").await.unwrap();
+        assert_eq!(table.angles, [ Some(SERVO_STATE_STANDBY); 8 ]);
+
+        let closed = table.pose("closed").unwrap();
+        table.move_to_angles(&closed, &table.transition.clone(), Duration::from_millis(5)).await.unwrap();
+        assert_eq!(table.angles, closed.map(Some));
+        assert_eq!(table.signals, [ SERVO_SIG_MAX; 8 ]);
+    }
+
+    #[test]
+    fn setup_passes_self_test() {
+        let (board, mut table) = table();
+        table.setup().unwrap();
+
+        let registers = board.pca9685().lock().unwrap().registers;
+        assert_eq!(registers[REG_MODE1 as usize] & MODE1_SLEEP, 0);
+        assert_eq!(registers[REG_PRE_SCALE as usize], PRESCALE);
+
+        table.set_servo_signal(3, 300).unwrap();
+        assert_eq!(board.pca9685().lock().unwrap().channel_off(3), 300);
+        assert_eq!(table.signals[3], 300);
+    }
+
+    #[test]
+    fn missing_board() {
+        let (board, mut table) = table();
+        board.pca9685().lock().unwrap().connected = false;
+
+        assert!(matches!(table_error(table.setup().unwrap_err()), ServoTableError::BoardMissing));
+        assert!(matches!(table.self_test(), Err(ServoTableError::BoardMissing)));
+        assert!(matches!(table.set_servo_signal(0, 300), Err(ServoTableError::BoardMissing)));
+        assert!(matches!(table.set_aux_signal(8, 300), Err(ServoTableError::BoardMissing)));
+
+        // Failed writes do not change the recorded state
+        assert_eq!(table.signals[0], 0);
+    }
+
+    #[test]
+    fn register_mismatch() {
+        let (board, mut table) = table();
+        table.setup().unwrap();
+
+        // The board has been reset, e.g. by a brown-out
+        board.pca9685().lock().unwrap().registers[REG_MODE1 as usize] = 0x11;
+        assert!(matches!(table.self_test(), Err(ServoTableError::RegisterMismatch(REG_MODE1, 0x01, 0x11))));
+
+        board.pca9685().lock().unwrap().registers[REG_MODE1 as usize] = 0x01;
+        board.pca9685().lock().unwrap().registers[REG_PRE_SCALE as usize] = 0x1E;
+        assert!(matches!(table.self_test(), Err(ServoTableError::RegisterMismatch(REG_PRE_SCALE, PRESCALE, 0x1E))));
+    }
+
+    #[tokio::test]
+    async fn errors_propagate_from_async_moves() {
+        let (board, mut table) = table();
+        table.setup().unwrap();
+        table.move_to_pose("closed").await.unwrap();
+
+        board.pca9685().lock().unwrap().connected = false;
+
+        assert!(matches!(table.move_to_pose("open").await, Err(ServoTableError::BoardMissing)));
+        assert!(matches!(table.roll_servos(10.0).await, Err(ServoTableError::BoardMissing)));
+        assert!(matches!(table.move_to_pose("unknown").await, Err(ServoTableError::UnknownPose(_))));
+
+        // The servos are still known to be closed
+        assert_eq!(table.angles, [ Some(SERVO_STATE_CLOSED); 8 ]);
+    }
+}
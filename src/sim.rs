@@ -0,0 +1,222 @@
+use core::f32::consts::PI;
+use core::time::Duration;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::thread::JoinHandle;
+
+use serde::{Serialize, Deserialize};
+use syact::{MicroSteps, StepperConst};
+use syunit::*;
+
+use crate::END_SWITCH_TRIGGER;
+use crate::config::{DrakeConfig, DrakeHardware};
+use crate::hal::{Hal, VirtualBoard, VirtualPinState};
+
+/// Time simulated button presses are held longer than required and the pause between them
+pub const SIM_PRESS_MARGIN : Duration = Duration::from_millis(200);
+
+/// Simulation parameters of a single axis
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SimAxisConfig {
+    /// Physical position of the axis when the simulation starts
+    pub start : Gamma,
+    /// Position at (and beyond) which the positive end switch is triggered
+    pub switch_pos : Option<Gamma>,
+    /// Position at (and below) which the negative end switch is triggered
+    pub switch_neg : Option<Gamma>
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SimConfig {
+    pub x : SimAxisConfig,
+    pub y : SimAxisConfig,
+    pub z : SimAxisConfig
+}
+
+impl SimConfig {
+    pub fn parse_from_file(path : &str) -> Result<Self, syact::Error> {
+        Ok(serde_json::from_str::<Self>(
+            std::fs::read_to_string(path)?.as_str()
+        )?)
+    }
+
+    /// Places the end switches where the measurements of the given config expect them, starting in the home position
+    pub fn from_config(config : &DrakeConfig) -> Self {
+        let axis = |index : usize, meas : &syact::meas::SimpleMeasParams| {
+            let positive = meas.max_dist > Delta::ZERO;
+
+            SimAxisConfig {
+                start: Gamma(config.home[index].0),
+                switch_pos: if positive { Some(meas.set_gamma) } else { None },
+                switch_neg: if positive { None } else { Some(meas.set_gamma) }
+            }
+        };
+
+        Self {
+            x: axis(0, &config.meas_data_x),
+            y: axis(1, &config.meas_data_y),
+            z: axis(2, &config.meas_data_z)
+        }
+    }
+}
+
+/// A simulated axis, counting the step pulses sent to its driver
+pub struct SimAxis {
+    steps : AtomicI64,
+    mm_per_step : f32,
+    start : Gamma,
+
+    switch_pos : Option<(Gamma, Arc<VirtualPinState>)>,
+    switch_neg : Option<(Gamma, Arc<VirtualPinState>)>
+}
+
+impl SimAxis {
+    /// Net amount of steps driven since the start of the simulation (positive in CW direction)
+    pub fn steps(&self) -> i64 {
+        self.steps.load(Ordering::Relaxed)
+    }
+
+    /// The physical position of the axis
+    pub fn gamma(&self) -> Gamma {
+        self.start + Delta(self.steps() as f32 * self.mm_per_step)
+    }
+
+    fn step(&self, dir : bool) {
+        self.steps.fetch_add(if dir { 1 } else { -1 }, Ordering::Relaxed);
+        self.update_switches();
+    }
+
+    fn update_switches(&self) {
+        let gamma = self.gamma();
+
+        if let Some((pos, pin)) = &self.switch_pos {
+            pin.set_level(if gamma >= *pos { END_SWITCH_TRIGGER } else { !END_SWITCH_TRIGGER });
+        }
+
+        if let Some((pos, pin)) = &self.switch_neg {
+            pin.set_level(if gamma <= *pos { END_SWITCH_TRIGGER } else { !END_SWITCH_TRIGGER });
+        }
+    }
+}
+
+/// A virtual Drake, emulating the motors and end switches on top of a `VirtualBoard`
+pub struct VirtualDrake {
+    pub board : VirtualBoard,
+    pub axes : [Arc<SimAxis>; 3],
+
+    start_switch : u8
+}
+
+impl VirtualDrake {
+    pub fn new(hw : &DrakeHardware, config : &DrakeConfig, sim : &SimConfig) -> Self {
+        let board = VirtualBoard::new();
+
+        let axis = |step : u8, dir : u8, micro : MicroSteps, ratio : f32, sim_axis : &SimAxisConfig, pin_pos : Option<u8>, pin_neg : Option<u8>| {
+            let steps_per_rev = StepperConst::MOT_17HE15_1504S.number_steps as f32 * micro.as_u8() as f32;
+
+            // Switches without a simulated position are never triggered
+            for pin in pin_pos.iter().chain(pin_neg.iter()) {
+                board.pin(*pin).set_level(!END_SWITCH_TRIGGER);
+            }
+
+            let axis = Arc::new(SimAxis {
+                steps: AtomicI64::new(0),
+                mm_per_step: 2.0 * PI / steps_per_rev * ratio,
+                start: sim_axis.start,
+
+                switch_pos: sim_axis.switch_pos.zip(pin_pos).map(|(pos, pin)| (pos, board.pin(pin))),
+                switch_neg: sim_axis.switch_neg.zip(pin_neg).map(|(pos, pin)| (pos, board.pin(pin)))
+            });
+            axis.update_switches();
+
+            // Count every rising edge of the step pin in the direction given by the dir pin
+            let dir_pin = board.pin(dir);
+            let axis_ref = axis.clone();
+            board.pin(step).on_change(Box::new(move |level| {
+                if level {
+                    axis_ref.step(dir_pin.level());
+                }
+            }));
+
+            axis
+        };
+
+        let axes = [
+            axis(hw.x_step, hw.x_dir, hw.x_microsteps, config.ratio_x, &sim.x, Some(hw.x_meas_pos), hw.x_meas_neg),
+            axis(hw.y_step, hw.y_dir, hw.y_microsteps, config.ratio_y, &sim.y, Some(hw.y_meas_pos), hw.y_meas_neg),
+            axis(hw.z_step, hw.z_dir, hw.z_microsteps, config.ratio_z, &sim.z, hw.z_meas_pos, Some(hw.z_meas_neg))
+        ];
+
+        Self {
+            board,
+            axes,
+
+            start_switch: hw.ut_start_switch
+        }
+    }
+
+    /// Default pin layout for simulations, matching the one in `scripts/env.sh`
+    pub fn hardware() -> DrakeHardware {
+        DrakeHardware {
+            voltage: 24.0,
+
+            x_step: 24,
+            y_step: 5,
+            z_step: 16,
+
+            x_dir: 15,
+            y_dir: 25,
+            z_dir: 6,
+
+            x_meas_pos: 23,
+            y_meas_pos: 12,
+            z_meas_neg: 19,
+
+            x_meas_neg: None,
+            y_meas_neg: None,
+            z_meas_pos: None,
+
+            x_microsteps: MicroSteps::from(8),
+            y_microsteps: MicroSteps::from(8),
+            z_microsteps: MicroSteps::from(1),
+
+            ut_start_led: 27,
+            ut_start_switch: 26,
+            ut_halt_led: 13,
+            ut_halt_switch: 21
+        }
+    }
+
+    pub fn hal(&self) -> Hal {
+        Hal::Virtual(self.board.clone())
+    }
+
+    /// The physical positions of all axes
+    pub fn gammas(&self) -> [Gamma; 3] {
+        [ self.axes[0].gamma(), self.axes[1].gamma(), self.axes[2].gamma() ]
+    }
+
+    /// Holds the start button down, so every prompt for it passes immediately
+    pub fn hold_start(&self) {
+        self.board.set_level(self.start_switch, true);
+    }
+
+    pub fn release_start(&self) {
+        self.board.set_level(self.start_switch, false);
+    }
+
+    /// Releases the start button and presses it on its own thread, every press given as the time it is held and the pause after it
+    pub fn play_start_presses(&self, presses : Vec<(Duration, Duration)>) -> JoinHandle<()> {
+        let switch = self.board.pin(self.start_switch);
+        switch.set_level(false);
+
+        std::thread::spawn(move || {
+            for (hold, pause) in presses {
+                switch.set_level(true);
+                std::thread::sleep(hold);
+                switch.set_level(false);
+                std::thread::sleep(pause);
+            }
+        })
+    }
+}
@@ -0,0 +1,220 @@
+use std::time::{Duration, Instant};
+
+use syact::prelude::*;
+
+use crate::hal::HalOutputPin;
+use crate::motion::{Block, SpeedOverride, MIN_SPEED};
+
+/// Time the step pin is held high for a single step
+pub const STEP_PULSE_TIME : Duration = Duration::from_micros(5);
+/// Steps closer than this are waited for by spinning, as sleeping overshoots
+pub const SPIN_TIME : Duration = Duration::from_micros(300);
+
+/// Distributes the steps of a straight line between the X- and Y-axis, yielding which axes step for every step of the faster axis
+#[derive(Clone, Debug)]
+pub struct LineSteps {
+    steps : [u64; 2],
+    count : u64,
+    index : u64
+}
+
+impl LineSteps {
+    pub fn new(steps : [u64; 2]) -> Self {
+        Self { steps, count: steps[0].max(steps[1]), index: 0 }
+    }
+
+    /// Amount of steps of the faster axis
+    pub fn major_steps(&self) -> u64 {
+        self.count
+    }
+
+    /// Steps of the given axis done after `index` steps of the faster axis, rounded to the closest step
+    fn done(&self, axis : usize, index : u64) -> u64 {
+        (index * self.steps[axis] + self.count / 2) / self.count
+    }
+}
+
+impl Iterator for LineSteps {
+    type Item = [bool; 2];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.count {
+            return None;
+        }
+
+        let index = self.index;
+        self.index += 1;
+
+        Some([ 0, 1 ].map(|axis| self.done(axis, index + 1) > self.done(axis, index)))
+    }
+}
+
+/// Time between the steps `index - 1` and `index` of a block split into `count` steps, following its speed profile scaled by `scale`
+pub fn step_interval(block : &Block, index : u64, count : u64, scale : f32) -> Duration {
+    let step_length = block.length / count as f32;
+    // Speed in the middle of the step
+    let speed = block.speed_at((index as f32 - 0.5) * step_length) * scale;
+
+    Duration::from_secs_f32(step_length / speed.max(MIN_SPEED))
+}
+
+/// Waits until the given instant, sleeping for the most part and spinning for the rest
+fn wait_until(deadline : Instant) {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+
+    if remaining > SPIN_TIME {
+        std::thread::sleep(remaining - SPIN_TIME);
+    }
+
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
+
+/// Step generator driving the X- and Y-axis together, following the planned speed profiles without stopping between blocks
+///
+/// The generator pulses the step pins of the axes itself, sharing them with the steppers of the robot.
+pub struct XyStepper {
+    step_pins : [HalOutputPin; 2],
+    dir_pins : [HalOutputPin; 2],
+    /// Distance of a single (micro)step of each axis (mm)
+    mm_per_step : [f32; 2],
+    interruptors : [Vec<Box<dyn Interruptor + Send>>; 2],
+
+    /// Time of the last step, kept while the pen does not stop between blocks
+    last_step : Option<Instant>
+}
+
+impl XyStepper {
+    pub fn new(step_pins : [HalOutputPin; 2], dir_pins : [HalOutputPin; 2], mm_per_step : [f32; 2]) -> Self {
+        Self {
+            step_pins,
+            dir_pins,
+            mm_per_step,
+            interruptors: [ Vec::new(), Vec::new() ],
+
+            last_step: None
+        }
+    }
+
+    /// Adds an interruptor checked before every step of the given axis (0: X, 1: Y)
+    pub fn add_interruptor(&mut self, axis : usize, interruptor : Box<dyn Interruptor + Send>) {
+        self.interruptors[axis].push(interruptor);
+    }
+
+    /// Returns true if any interruptor of the axis stops a step into the given direction
+    fn interrupted(&mut self, axis : usize, dir : Direction, pos : f32) -> bool {
+        self.interruptors[axis].iter_mut().any(|intr| {
+            !matches!(intr.dir(), Some(d) if d != dir) && intr.check(Gamma(pos)).is_some()
+        })
+    }
+
+    fn pulse(&mut self, axis : usize) {
+        let start = Instant::now();
+
+        self.step_pins[axis].write(true);
+        wait_until(start + STEP_PULSE_TIME);
+        self.step_pins[axis].write(false);
+    }
+
+    /// Drives from the given position (mm) to the end of the block, blocking the thread, and returns the position reached
+    ///
+    /// The block has been planned with the override factor `planned_override`, a lower override slows the steps down right away.
+    /// If the block does not end at standstill, the timing continues seamlessly into the next block. An interruptor stops the
+    /// axes immediately.
+    pub fn run_block(&mut self, block : &Block, from : [f32; 2], speed_override : &SpeedOverride, planned_override : f32) -> [f32; 2] {
+        let delta = [ 0, 1 ].map(|axis| {
+            ((block.to[axis] / self.mm_per_step[axis]).round() - (from[axis] / self.mm_per_step[axis]).round()) as i64
+        });
+        let dirs = delta.map(|steps| Direction::from_bool(steps >= 0));
+        let sign = delta.map(|steps| steps.signum() as f32);
+
+        for (pin, dir) in self.dir_pins.iter_mut().zip(dirs) {
+            pin.write(dir.as_bool());
+        }
+
+        let mut pos = from;
+        let line = LineSteps::new(delta.map(|steps| steps.unsigned_abs()));
+        let count = line.major_steps();
+
+        let mut last = match self.last_step {
+            Some(last) if block.entry_speed > 0.0 => last,
+            _ => Instant::now()
+        };
+
+        for (index, axes) in line.enumerate() {
+            for axis in 0 .. 2 {
+                if axes[axis] && self.interrupted(axis, dirs[axis], pos[axis]) {
+                    self.last_step = None;
+                    return pos;
+                }
+            }
+
+            let scale = (speed_override.factor() / planned_override).min(1.0);
+            let deadline = last + step_interval(block, index as u64 + 1, count, scale);
+
+            wait_until(deadline);
+            // Late steps continue from now instead of catching up
+            last = deadline.max(Instant::now());
+
+            for axis in 0 .. 2 {
+                if axes[axis] {
+                    self.pulse(axis);
+                    pos[axis] += sign[axis] * self.mm_per_step[axis];
+                }
+            }
+        }
+
+        self.last_step = if block.exit_speed > 0.0 { Some(last) } else { None };
+        pos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::motion::{plan_polyline, PlannerConfig};
+
+    #[test]
+    fn line_steps() {
+        let steps : Vec<[bool; 2]> = LineSteps::new([ 8, 3 ]).collect();
+
+        assert_eq!(steps.len(), 8);
+        assert!(steps.iter().all(|s| s[0]));
+        assert_eq!(steps.iter().filter(|s| s[1]).count(), 3);
+
+        // The steps of the slower axis are spread over the line
+        assert!(!steps[0][1] && !steps[7][1]);
+
+        assert_eq!(LineSteps::new([ 0, 5 ]).filter(|s| !s[0] && s[1]).count(), 5);
+        assert_eq!(LineSteps::new([ 0, 0 ]).count(), 0);
+    }
+
+    #[test]
+    fn continuous_intervals() {
+        let config = PlannerConfig {
+            max_speed: [ 100.0, 100.0 ],
+            max_accel: [ 1000.0, 1000.0 ],
+            junction_deviation: 0.05,
+            lookahead: 16
+        };
+        let blocks = plan_polyline(&[ [ 0.0, 0.0 ], [ 10.0, 0.0 ], [ 20.0, 0.0 ] ], 50.0, &config);
+        let count = 1000;
+
+        // The pen passes the collinear point at full speed, without stopping
+        let last = step_interval(&blocks[0], count, count, 1.0);
+        let first = step_interval(&blocks[1], 1, count, 1.0);
+        let cruise = Duration::from_secs_f32(10.0 / count as f32 / 50.0);
+
+        assert!((last.as_secs_f32() / cruise.as_secs_f32() - 1.0).abs() < 0.01, "{:?} != {:?}", last, cruise);
+        assert!((first.as_secs_f32() / cruise.as_secs_f32() - 1.0).abs() < 0.01, "{:?} != {:?}", first, cruise);
+
+        // Starting and ending at standstill is slower
+        assert!(step_interval(&blocks[0], 1, count, 1.0) > cruise * 5);
+        assert!(step_interval(&blocks[1], count, count, 1.0) > cruise * 5);
+
+        // A lower override stretches the intervals
+        assert!(step_interval(&blocks[1], 1, count, 0.5) > cruise);
+    }
+}
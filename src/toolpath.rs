@@ -0,0 +1,128 @@
+use core::fmt::Write;
+
+use crate::drawing::{Drawing, LinesFile};
+use crate::drawing::transform::DrawingTransform;
+
+/// Colour of pen-down moves in exported SVGs
+pub const SVG_DRAW_COLOR : &str = "#000000";
+/// Colour of travel moves in exported SVGs
+pub const SVG_TRAVEL_COLOR : &str = "#f0a0a0";
+/// Margin around the drawing in exported SVGs (mm)
+pub const SVG_MARGIN : f32 = 5.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveKind {
+    /// The pen is down and draws
+    Draw,
+    /// The pen is lifted and travels to the start of the next stroke
+    Travel
+}
+
+/// A single straight move of the pen in the drawing plane (mm, relative to the drawing origin)
+#[derive(Clone, Copy, Debug)]
+pub struct ToolpathMove {
+    pub kind : MoveKind,
+    pub from : [f32; 2],
+    pub to : [f32; 2]
+}
+
+/// A record of the pen moves performed during a job
+#[derive(Clone, Debug, Default)]
+pub struct Toolpath {
+    pub moves : Vec<ToolpathMove>
+}
+
+impl Toolpath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the toolpath a `LinesFile` is expected to produce, traveling whenever two lines do not connect
+    pub fn from_lines(lines : &LinesFile, transform : &DrawingTransform) -> Self {
+        Self::from_drawing(&Drawing::from_lines(lines), transform)
+    }
+
+    /// Creates the toolpath a `Drawing` is expected to produce, traveling whenever two strokes do not connect
+    pub fn from_drawing(drawing : &Drawing, transform : &DrawingTransform) -> Self {
+        let mut toolpath = Self::new();
+        let mut last_point : Option<[f32; 2]> = None;
+
+        for stroke in &drawing.strokes {
+            let points : Vec<[f32; 2]> = stroke.iter().map(|&p| transform.apply(p)).collect();
+
+            if let (Some(last), Some(&first)) = (last_point, points.first()) {
+                if last != first {
+                    toolpath.push(MoveKind::Travel, last, first);
+                }
+            }
+
+            for w in points.windows(2) {
+                toolpath.push(MoveKind::Draw, w[0], w[1]);
+            }
+
+            if let Some(&end) = points.last() {
+                last_point = Some(end);
+            }
+        }
+
+        toolpath
+    }
+
+    pub fn push(&mut self, kind : MoveKind, from : [f32; 2], to : [f32; 2]) {
+        self.moves.push(ToolpathMove { kind, from, to })
+    }
+
+    /// Total length of all moves of the given kind (mm)
+    pub fn distance(&self, kind : MoveKind) -> f32 {
+        self.moves.iter().filter(|m| m.kind == kind).map(|m| {
+            ((m.to[0] - m.from[0]).powi(2) + (m.to[1] - m.from[1]).powi(2)).sqrt()
+        }).sum()
+    }
+
+    /// Returns the minimum and maximum corner of all moves, `None` if the toolpath is empty
+    pub fn bounds(&self) -> Option<([f32; 2], [f32; 2])> {
+        let mut points = self.moves.iter().flat_map(|m| [ m.from, m.to ]);
+        let first = points.next()?;
+
+        Some(points.fold((first, first), |(min, max), p| {
+            ([ min[0].min(p[0]), min[1].min(p[1]) ], [ max[0].max(p[0]), max[1].max(p[1]) ])
+        }))
+    }
+
+    /// Renders the toolpath as an SVG document, pen-down moves in black and travel moves in a faint colour
+    pub fn to_svg(&self) -> String {
+        let (min, max) = self.bounds().unwrap_or(([0.0; 2], [0.0; 2]));
+        let width = max[0] - min[0] + 2.0 * SVG_MARGIN;
+        let height = max[1] - min[1] + 2.0 * SVG_MARGIN;
+
+        let mut svg = String::new();
+
+        writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}mm\" height=\"{height}mm\" viewBox=\"{} {} {width} {height}\">",
+            min[0] - SVG_MARGIN, min[1] - SVG_MARGIN).unwrap();
+
+        for (kind, color) in [ (MoveKind::Travel, SVG_TRAVEL_COLOR), (MoveKind::Draw, SVG_DRAW_COLOR) ] {
+            let mut data = String::new();
+            let mut last_point : Option<[f32; 2]> = None;
+
+            for m in self.moves.iter().filter(|m| m.kind == kind) {
+                if last_point != Some(m.from) {
+                    write!(data, "M{} {} ", m.from[0], m.from[1]).unwrap();
+                }
+
+                write!(data, "L{} {} ", m.to[0], m.to[1]).unwrap();
+                last_point = Some(m.to);
+            }
+
+            writeln!(svg, "  <path d=\"{}\" fill=\"none\" stroke=\"{color}\" stroke-width=\"0.3\" stroke-linecap=\"round\" stroke-linejoin=\"round\"/>",
+                data.trim_end()).unwrap();
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    pub fn save_svg(&self, path : &str) -> Result<(), syact::Error> {
+        std::fs::write(path, self.to_svg())?;
+        Ok(())
+    }
+}
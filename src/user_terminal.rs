@@ -1,98 +1,357 @@
-use rppal::gpio::{Gpio, InputPin, Level, OutputPin};
+use core::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
 use syact::Setup;
 
+use crate::hal::{Hal, HalInputPin, HalOutputPin};
+
+/// Interval in which button monitors read their button
+pub const BUTTON_POLL_INTERVAL : Duration = Duration::from_millis(2);
+/// Interval in which a button is read while waiting for it
+pub const WAIT_POLL_INTERVAL : Duration = Duration::from_millis(5);
+/// Time the level of a button has to be stable before a change is accepted
+pub const DEBOUNCE_TIME : Duration = Duration::from_millis(20);
+/// Time a button has to be held down to count as a long press
+pub const LONG_PRESS_TIME : Duration = Duration::from_millis(1000);
+
+// Buttons
+    /// Kind of a button press
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Press {
+        /// Released before `LONG_PRESS_TIME`
+        Short,
+        /// Held down for at least `LONG_PRESS_TIME`
+        Long
+    }
+
+    /// Filters the bouncing of a button, only accepting levels that have been stable for `DEBOUNCE_TIME`
+    pub struct Debouncer {
+        level : bool,
+        candidate : bool,
+        since : Instant
+    }
+
+    impl Debouncer {
+        pub fn new(level : bool) -> Self {
+            Self { level, candidate: level, since: Instant::now() }
+        }
+
+        /// The debounced level
+        pub fn level(&self) -> bool {
+            self.level
+        }
+
+        /// Feeds a new reading of the button, returning the debounced level
+        pub fn update(&mut self, level : bool) -> bool {
+            let now = Instant::now();
+
+            if level != self.candidate {
+                self.candidate = level;
+                self.since = now;
+            } else if (level != self.level) && (now.duration_since(self.since) >= DEBOUNCE_TIME) {
+                self.level = level;
+            }
+
+            self.level
+        }
+    }
+
+    /// Waits for the next press of the button, returning `None` if no press started before the timeout
+    ///
+    /// A button already held down when waiting starts counts as pressed. Presses are only reported once the button has been 
+    /// released (debounced), so the next wait does not pick up the same press again. The timeout does not cut off a started press.
+    pub async fn wait_for_press(switch : &Mutex<HalInputPin>, timeout : Option<Duration>) -> Option<Press> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut debouncer = Debouncer::new(false);
+        let mut pressed_at : Option<Instant> = None;
+
+        loop {
+            let level = debouncer.update(switch.lock().unwrap().is_high());
+
+            match (level, pressed_at) {
+                (true, None) => pressed_at = Some(Instant::now()),
+                (true, Some(_)) => { },
+                (false, Some(start)) => return Some(if start.elapsed() >= LONG_PRESS_TIME {
+                    Press::Long
+                } else {
+                    Press::Short
+                }),
+                (false, None) => if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    return None;
+                }
+            }
+
+            tokio::time::sleep(WAIT_POLL_INTERVAL).await;
+        }
+    }
+//
+
+// LEDs
+    /// Named blink patterns played by a `LedDriver`
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub enum LedPattern {
+        #[default]
+        Off,
+        Solid,
+        /// 1 Hz, used when waiting for the user
+        SlowBlink,
+        /// 4 Hz
+        FastBlink,
+        /// Two short flashes per second
+        Heartbeat,
+        /// The given amount of flashes followed by a pause, repeated, identifying a menu action
+        Code(u8),
+        /// Like `Code`, but identifying an error
+        ErrorCode(u8)
+    }
+
+    impl LedPattern {
+        /// Levels of the pattern and how long they are held, `None` holding the level until the pattern changes
+        pub fn steps(&self) -> Vec<(bool, Option<Duration>)> {
+            let ms = |millis| Some(Duration::from_millis(millis));
+
+            match self {
+                Self::Off => vec![ (false, None) ],
+                Self::Solid => vec![ (true, None) ],
+                Self::SlowBlink => vec![ (true, ms(500)), (false, ms(500)) ],
+                Self::FastBlink => vec![ (true, ms(125)), (false, ms(125)) ],
+                Self::Heartbeat => vec![ (true, ms(100)), (false, ms(150)), (true, ms(100)), (false, ms(650)) ],
+                Self::Code(count) | Self::ErrorCode(count) => {
+                    let mut steps : Vec<_> = (0 .. *count).flat_map(|_| [ (true, ms(200)), (false, ms(300)) ]).collect();
+                    steps.push((false, ms(1500)));
+                    steps
+                }
+            }
+        }
+    }
+
+    /// Plays `LedPattern`s on an LED, the pattern is played on its own thread so it keeps running while a move blocks the async runtime
+    pub struct LedDriver {
+        pattern : LedPattern,
+        sender : Option<Sender<LedPattern>>,
+        handle : Option<JoinHandle<()>>
+    }
+
+    impl LedDriver {
+        pub fn spawn(mut led : HalOutputPin) -> Self {
+            let (sender, receiver) : (_, Receiver<LedPattern>) = mpsc::channel();
+
+            let handle = std::thread::spawn(move || {
+                let mut pattern = LedPattern::Off;
+
+                'pattern: loop {
+                    for (level, time) in pattern.steps() {
+                        led.write(level);
+
+                        let next = match time {
+                            Some(time) => receiver.recv_timeout(time),
+                            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+                        };
+
+                        match next {
+                            Ok(new_pattern) => {
+                                pattern = new_pattern;
+                                continue 'pattern;
+                            },
+                            Err(RecvTimeoutError::Timeout) => { },
+                            Err(RecvTimeoutError::Disconnected) => break 'pattern
+                        }
+                    }
+                }
+
+                led.write(false);
+            });
+
+            Self {
+                pattern: LedPattern::Off,
+                sender: Some(sender),
+                handle: Some(handle)
+            }
+        }
+
+        pub fn pattern(&self) -> LedPattern {
+            self.pattern
+        }
+
+        /// Switches to the given pattern, restarting it if it is already playing
+        pub fn play(&mut self, pattern : LedPattern) {
+            self.pattern = pattern;
+
+            if let Some(sender) = &self.sender {
+                sender.send(pattern).ok();
+            }
+        }
+    }
+
+    impl Drop for LedDriver {
+        fn drop(&mut self) {
+            // Disconnecting the channel stops the thread
+            self.sender.take();
+
+            if let Some(handle) = self.handle.take() {
+                handle.join().ok();
+            }
+        }
+    }
+//
+
+/// Watches a button and calls the given function every time it gets pressed, stops when dropped
+///
+/// The button is polled on its own thread, so it is read even while a move blocks the async runtime. Readings are
+/// debounced, so a bouncing contact counts as a single press.
+pub struct ButtonMonitor {
+    running : Arc<AtomicBool>,
+    handle : Option<JoinHandle<()>>
+}
+
+impl ButtonMonitor {
+    pub fn spawn<F : Fn() + Send + 'static>(switch : Arc<Mutex<HalInputPin>>, on_press : F) -> Self {
+        let running = Arc::new(AtomicBool::new(true));
+        let running_ref = running.clone();
+
+        let handle = std::thread::spawn(move || {
+            // A button still held down when the monitor starts does not count as a press
+            let mut debouncer = Debouncer::new(switch.lock().unwrap().is_high());
+
+            while running_ref.load(Ordering::Relaxed) {
+                let pressed = debouncer.level();
+
+                if debouncer.update(switch.lock().unwrap().is_high()) && !pressed {
+                    on_press();
+                }
+
+                std::thread::sleep(BUTTON_POLL_INTERVAL);
+            }
+        });
+
+        Self {
+            running,
+            handle: Some(handle)
+        }
+    }
+}
+
+impl Drop for ButtonMonitor {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+
+        if let Some(handle) = self.handle.take() {
+            handle.join().ok();
+        }
+    }
+}
+
 pub struct UserTerminal {
-    switch_start : InputPin,
-    led_start : OutputPin,
+    /// Shared with the pause monitor
+    switch_start : Arc<Mutex<HalInputPin>>,
+    led_start : LedDriver,
 
-    switch_halt : InputPin,
-    led_halt : OutputPin,
+    /// Shared with the halt monitor
+    switch_halt : Arc<Mutex<HalInputPin>>,
+    led_halt : LedDriver,
 }
 
 impl UserTerminal {
-    pub fn new(gpio : &Gpio, switch_start_pin : u8, led_start_pin : u8, switch_halt_pin : u8, led_halt_pin : u8) -> Result<Self, syact::Error> {
+    pub fn new(hal : &Hal, switch_start_pin : u8, led_start_pin : u8, switch_halt_pin : u8, led_halt_pin : u8) -> Result<Self, syact::Error> {
         Ok(Self {
-            switch_start: gpio.get(switch_start_pin)?.into_input(),
-            led_start: gpio.get(led_start_pin)?.into_output_low(),
+            switch_start: Arc::new(Mutex::new(hal.input(switch_start_pin)?)),
+            led_start: LedDriver::spawn(hal.output_low(led_start_pin)?),
             
-            switch_halt: gpio.get(switch_halt_pin)?.into_input(),
-            led_halt: gpio.get(led_halt_pin)?.into_output_low()
+            switch_halt: Arc::new(Mutex::new(hal.input(switch_halt_pin)?)),
+            led_halt: LedDriver::spawn(hal.output_low(led_halt_pin)?)
         })
     }
 
     // Buttons
         pub fn check_start(&self) -> bool {
-            self.switch_start.is_high()
+            self.switch_start.lock().unwrap().is_high()
         }
 
         pub fn check_halt(&self) -> bool {
-            self.switch_halt.is_high()
+            self.switch_halt.lock().unwrap().is_high()
+        }
+
+        /// The start button, to be watched by a `ButtonMonitor`
+        pub fn start_switch(&self) -> Arc<Mutex<HalInputPin>> {
+            self.switch_start.clone()
+        }
+
+        /// The halt button, to be watched by a `ButtonMonitor`
+        pub fn halt_switch(&self) -> Arc<Mutex<HalInputPin>> {
+            self.switch_halt.clone()
         }
 
-        pub fn prompt_start(&mut self) {
+        /// Waits for the start button to be pressed, see `wait_for_press()`
+        pub async fn wait_for_start(&self, timeout : Option<Duration>) -> Option<Press> {
+            wait_for_press(&self.switch_start, timeout).await
+        }
+
+        /// Waits for the halt button to be pressed, see `wait_for_press()`
+        pub async fn wait_for_halt(&self, timeout : Option<Duration>) -> Option<Press> {
+            wait_for_press(&self.switch_halt, timeout).await
+        }
+
+        /// Blinks the start LED until the start button is pressed
+        pub async fn prompt_start(&mut self) -> Press {
             log::debug!("> Waiting for start button ... ");
 
-            let mut counter = 0;
+            let pattern = self.led_start.pattern();
+            self.led_start.play(LedPattern::SlowBlink);
 
-            loop {
-                if (counter % 20) == 0 {
-                    self.set_start_led(
-                        !self.is_start_led_on()
-                    );
-                }
-    
-                if self.check_start() {
-                    self.set_start_led(false);
-                    break;
-                }
-    
-                std::thread::sleep(core::time::Duration::from_millis(25));
-                counter += 1;
-            }
+            // Without a timeout there is always a press
+            let press = self.wait_for_start(None).await.unwrap_or(Press::Short);
 
-            log::debug!("> Start button pressed!");
+            self.led_start.play(pattern);
+            log::debug!("> Start button pressed! ({:?})", press);
+
+            press
         }
 
-        pub fn prompt_halt(&mut self) {
+        /// Blinks the halt LED until the halt button is pressed
+        pub async fn prompt_halt(&mut self) -> Press {
             log::debug!("> Waiting for halt button ... ");
 
-            let mut counter = 0;
+            let pattern = self.led_halt.pattern();
+            self.led_halt.play(LedPattern::SlowBlink);
 
-            loop {
-                if (counter % 20) == 0 {
-                    self.set_halt_led(
-                        !self.is_halt_led_on()
-                    );
-                }
-    
-                if self.check_halt() {
-                    self.set_halt_led(false);
-                    break;
-                }
-    
-                std::thread::sleep(core::time::Duration::from_millis(25));
-                counter += 1;
-            }
+            let press = self.wait_for_halt(None).await.unwrap_or(Press::Short);
 
-            log::debug!("> Halt button pressed!");
+            self.led_halt.play(pattern);
+            log::debug!("> Halt button pressed! ({:?})", press);
+
+            press
         }
     // 
 
     // LEDS
-        pub fn is_start_led_on(&self) -> bool {
-            self.led_start.is_set_high()
+        pub fn start_pattern(&self) -> LedPattern {
+            self.led_start.pattern()
         }
 
-        pub fn set_start_led(&mut self, value : bool) {
-            self.led_start.write(Level::from(value))
+        pub fn set_start_pattern(&mut self, pattern : LedPattern) {
+            self.led_start.play(pattern)
+        }
+
+        pub fn halt_pattern(&self) -> LedPattern {
+            self.led_halt.pattern()
+        }
+
+        pub fn set_halt_pattern(&mut self, pattern : LedPattern) {
+            self.led_halt.play(pattern)
         }
 
-        pub fn is_halt_led_on(&self) -> bool {
-            self.led_halt.is_set_high()
+        /// Turns the start LED on (solid) or off
+        pub fn set_start_led(&mut self, value : bool) {
+            self.set_start_pattern(if value { LedPattern::Solid } else { LedPattern::Off })
         }
 
+        /// Turns the halt LED on (solid) or off
         pub fn set_halt_led(&mut self, value : bool) {
-            self.led_start.write(Level::from(value))
+            self.set_halt_pattern(if value { LedPattern::Solid } else { LedPattern::Off })
         }
     // 
 }
@@ -107,4 +366,54 @@ impl Setup for UserTerminal {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::hal::VirtualBoard;
+
+    /// Holds the given pin of the board down for the given time on its own thread
+    fn press(board : &VirtualBoard, pin : u8, hold : Duration) -> JoinHandle<()> {
+        let state = board.pin(pin);
+
+        std::thread::spawn(move || {
+            state.set_level(true);
+            std::thread::sleep(hold);
+            state.set_level(false);
+        })
+    }
+
+    #[tokio::test]
+    async fn timeout_without_press() {
+        let board = VirtualBoard::new();
+        let switch = Mutex::new(HalInputPin::Virtual(board.input(1)));
+
+        assert_eq!(wait_for_press(&switch, Some(Duration::from_millis(50))).await, None);
+    }
+
+    #[tokio::test]
+    async fn short_press() {
+        let board = VirtualBoard::new();
+        let switch = Mutex::new(HalInputPin::Virtual(board.input(1)));
+
+        let presser = press(&board, 1, Duration::from_millis(150));
+        assert_eq!(wait_for_press(&switch, Some(Duration::from_millis(500))).await, Some(Press::Short));
+        presser.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn long_press_waits_for_release() {
+        let board = VirtualBoard::new();
+        let switch = Mutex::new(HalInputPin::Virtual(board.input(1)));
+        let start = Instant::now();
+
+        let presser = press(&board, 1, LONG_PRESS_TIME + Duration::from_millis(300));
+        assert_eq!(wait_for_press(&switch, None).await, Some(Press::Long));
+        assert!(start.elapsed() >= LONG_PRESS_TIME + Duration::from_millis(300));
+        presser.join().unwrap();
+
+        // The same press is not reported again
+        assert_eq!(wait_for_press(&switch, Some(Duration::from_millis(100))).await, None);
+    }
+}
@@ -0,0 +1,112 @@
+use syact::prelude::*;
+use sybot::prelude::*;
+
+use drake::{drake_robot_new, DrakeRobot, DrakeStation};
+use drake::config::DrakeConfig;
+use drake::drawing::{Drawing, DrawingUnit};
+use drake::drawing::transform::DrawingTransform;
+use drake::journal::{DrawingOptions, JobJournal, Journal, JOURNAL_FILE};
+use drake::job::JobSettings;
+use drake::routines::{draw_drawing, start_drawing};
+use drake::sim::{SimConfig, VirtualDrake};
+
+const CONFIG_PATH : &str = concat!(env!("CARGO_MANIFEST_DIR"), "/config/drake.example.json");
+
+/// Maximum distance between the simulated and the expected position of an axis (mm)
+const POS_TOLERANCE : f32 = 0.1;
+
+/// Creates a simulated drake, homed with the lifted pen above the drawing origin
+async fn start() -> (VirtualDrake, DrakeConfig, DrakeStation, DrakeRobot) {
+    let config = DrakeConfig::parse_from_file(CONFIG_PATH).unwrap();
+    let hardware = VirtualDrake::hardware();
+
+    let drake = VirtualDrake::new(&hardware, &config, &SimConfig::from_config(&config));
+    let hal = drake.hal();
+
+    let mut stat = DrakeStation::new(&hardware, &config, &hal).unwrap();
+    let mut rob = drake_robot_new(&hardware, &config, &hal, &stat).unwrap();
+
+    rob.comps_mut().set_config(StepperConfig::new(hardware.voltage, None));
+    rob.comps_mut().apply_inertias(&config.weights);
+    rob.setup().unwrap();
+    stat.setup().unwrap();
+
+    start_drawing(&mut stat, &mut rob).await.unwrap();
+
+    (drake, config, stat, rob)
+}
+
+/// Asserts that the pen of the simulated drake is at the given position relative to the drawing origin
+fn assert_pen_at(drake : &VirtualDrake, stat : &DrakeStation, point : [f32; 2]) {
+    let gammas = drake.gammas();
+    let expected = [ stat.drawing_origin[0].0 + point[0], stat.drawing_origin[1].0 + point[1] ];
+
+    assert!((gammas[0].0 - expected[0]).abs() < POS_TOLERANCE, "X: {:?} != {:?}", gammas, expected);
+    assert!((gammas[1].0 - expected[1]).abs() < POS_TOLERANCE, "Y: {:?} != {:?}", gammas, expected);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn home_and_draw_line() {
+    let (drake, config, mut stat, mut rob) = start().await;
+    assert_pen_at(&drake, &stat, [ 0.0, 0.0 ]);
+
+    stat.draw_polyline(&mut rob, &[ [ Phi(10.0), Phi(0.0) ], [ Phi(10.0), Phi(5.0) ] ], config.drawing_speed).await.unwrap();
+    assert_pen_at(&drake, &stat, [ 10.0, 5.0 ]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn draw_diagonal_line() {
+    let (drake, config, mut stat, mut rob) = start().await;
+
+    // Both axes move at the same time
+    stat.draw_to(&mut rob, [ Phi(8.0), Phi(6.0) ], config.drawing_speed).await.unwrap();
+    assert_pen_at(&drake, &stat, [ 8.0, 6.0 ]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn draw_square() {
+    let (drake, config, mut stat, mut rob) = start().await;
+
+    // The pen passes the corners without stopping, ending exactly on the last point
+    let square = [ [ Phi(6.0), Phi(0.0) ], [ Phi(6.0), Phi(6.0) ], [ Phi(0.0), Phi(6.0) ], [ Phi(0.0), Phi(0.0) ] ];
+    stat.draw_polyline(&mut rob, &square, config.drawing_speed).await.unwrap();
+    assert_pen_at(&drake, &stat, [ 0.0, 0.0 ]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resume_drawing_from_journal() {
+    let (drake, _, mut stat, mut rob) = start().await;
+
+    let dir = std::env::temp_dir().join(format!("drake_sim_resume_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("drawing.json").to_string_lossy().to_string();
+    std::fs::write(&source, "{ \"contour\": [] }").unwrap();
+
+    // A single stroke of 15 short lines, more than a checkpoint interval
+    let drawing = Drawing {
+        strokes: vec![ (0 ..= 15).map(|i| [ i as f32 * 0.4, (i % 2) as f32 * 0.4 ]).collect() ],
+        unit: DrawingUnit::Millimeter
+    };
+    let transform = DrawingTransform::default();
+
+    let job = JobJournal::new(&source, DrawingOptions::default(), transform.clone(), JobSettings::default(), drawing.line_count()).unwrap();
+    let mut journal = Journal::create(&dir.join(JOURNAL_FILE).to_string_lossy(), job).unwrap();
+
+    // The controller died after the first 4 lines
+    journal.checkpoint(4, 0.0).unwrap();
+
+    let mut journal = Journal::load(&journal.path).unwrap();
+    journal.job.verify().unwrap();
+    journal.job.verify_lines(drawing.line_count()).unwrap();
+
+    let remaining = drawing.skip_lines(journal.job.stats.lines_done);
+    assert_eq!(remaining.line_count(), 11);
+
+    draw_drawing(&mut stat, &mut rob, &remaining, &transform, Some(&mut journal)).await.unwrap();
+
+    let journal = Journal::load(&journal.path).unwrap();
+    assert_eq!(journal.job.stats.lines_done, 15);
+    assert!(journal.job.finished);
+
+    assert_pen_at(&drake, &stat, [ 6.0, 0.4 ]);
+}